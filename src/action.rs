@@ -0,0 +1,65 @@
+/// Player actions, each one backed by a `ButtonState` of the `GameState`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+    SoftDrop,
+    HardDrop,
+    Hold,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::RotateClockwise,
+        Action::RotateCounterClockwise,
+        Action::Rotate180,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::Hold,
+        Action::Pause,
+    ];
+
+    /// Single character identifying the action in replay files.
+    pub fn code(&self) -> char {
+        match self {
+            Action::MoveLeft => 'L',
+            Action::MoveRight => 'R',
+            Action::RotateClockwise => 'X',
+            Action::RotateCounterClockwise => 'Z',
+            Action::Rotate180 => 'F',
+            Action::SoftDrop => 'S',
+            Action::HardDrop => 'H',
+            Action::Hold => 'C',
+            Action::Pause => 'P',
+        }
+    }
+
+    pub fn from_code(code: char) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.code() == code)
+    }
+
+    /// Name of the action in the key bindings of the configuration file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::RotateClockwise => "rotate_clockwise",
+            Action::RotateCounterClockwise => "rotate_counterclockwise",
+            Action::Rotate180 => "rotate_180",
+            Action::SoftDrop => "soft_drop",
+            Action::HardDrop => "hard_drop",
+            Action::Hold => "hold",
+            Action::Pause => "pause",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
@@ -0,0 +1,354 @@
+mod render;
+
+use std::{
+    io::{self, Stdout, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    execute, terminal,
+};
+use tetris_rs::{
+    action::Action,
+    controls::{Control, Key, KeyBindings},
+    high_scores::HighScores,
+    mode::GameMode,
+    options::Options,
+    personal_best::{PersonalBests, SprintRecord},
+    replay::{Playback, Recorder, Replay},
+    GameState,
+};
+
+/// Number of frames a replay is rewound by with the left arrow.
+const SEEK_FRAMES: u64 = 5 * tetris_rs::FRAMES_PER_SECOND;
+
+/// Puts the terminal into raw mode on the alternate screen and restores it when dropped.
+struct Terminal {
+    key_release_events: bool,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+        // without key release events a held key can not be told apart from repeated presses
+        let key_release_events = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if key_release_events {
+            execute!(stdout, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        }
+        Ok(Self { key_release_events })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.key_release_events {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Translates a crossterm key code into the key names used by the key bindings.
+fn key(code: KeyCode) -> Option<Key> {
+    let key = match code {
+        KeyCode::Char(' ') => Key::Space,
+        KeyCode::Char(character) => Key::Char(character.to_ascii_uppercase()),
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Esc => Key::Escape,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::F(number) => Key::F(number),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Control bound to the key. Terminals report the numeric keypad as plain digits, so digits also trigger
+/// controls bound to keypad keys.
+fn control(keys: &KeyBindings, code: KeyCode) -> Option<Control> {
+    let key = key(code)?;
+    keys.control(key).or_else(|| match key {
+        Key::Char(digit @ '0'..='9') => keys.control(Key::Numpad(digit as u8 - b'0')),
+        _ => None,
+    })
+}
+
+/// What the frame loop asks a mode to handle.
+enum Tick {
+    Key(KeyCode, KeyEventKind),
+    /// Time to simulate the next frame, `FRAMES_PER_SECOND` times per second.
+    Frame,
+    /// Time to redraw the screen, at most `fps` times per second.
+    Draw,
+}
+
+/// What is shown after the game has ended.
+enum Ending {
+    /// The game itself (its end screen once it has ended).
+    None,
+    /// The game made it to the leaderboard at `rank`, the player types their name.
+    NameEntry { name: String, rank: usize },
+    /// Leaderboard of the game's mode, `highlight` is the rank of the entry just added.
+    Leaderboard { highlight: Option<usize> },
+}
+
+/// Passes every key event, `FRAMES_PER_SECOND` frames and up to `fps` redraws per second to `handle` until it
+/// returns `false`.
+fn run_frames(fps: u32, mut handle: impl FnMut(Tick, &mut Stdout) -> io::Result<bool>) -> io::Result<()> {
+    let frame_duration = Duration::from_secs(1) / tetris_rs::FRAMES_PER_SECOND as u32;
+    let draw_duration = Duration::from_secs(1) / fps;
+    let mut next_frame = Instant::now();
+    let mut next_draw = next_frame;
+    let mut stdout = io::stdout();
+
+    loop {
+        while event::poll(next_frame.min(next_draw).saturating_duration_since(Instant::now()))? {
+            if let Event::Key(KeyEvent { code, kind, .. }) = event::read()? {
+                if !handle(Tick::Key(code, kind), &mut stdout)? {
+                    return Ok(());
+                }
+            }
+        }
+
+        let now = Instant::now();
+        if now >= next_frame {
+            if !handle(Tick::Frame, &mut stdout)? {
+                return Ok(());
+            }
+            next_frame += frame_duration;
+        }
+        if now >= next_draw {
+            if !handle(Tick::Draw, &mut stdout)? {
+                return Ok(());
+            }
+            stdout.flush()?;
+            // redraws that are late are skipped rather than caught up with
+            next_draw = (next_draw + draw_duration).max(now);
+        }
+    }
+}
+
+/// Whether the key quits: the quit key, or Escape unless it is bound to something else.
+fn is_quit(keys: &KeyBindings, code: KeyCode) -> bool {
+    match control(keys, code) {
+        Some(control) => control == Control::Quit,
+        None => code == KeyCode::Esc,
+    }
+}
+
+/// Saves the results of the games as soon as they are known, like the windowed front end does, and keeps what
+/// is to be reported about it until the terminal is restored.
+struct Saves {
+    personal_bests: PersonalBests,
+    high_scores: HighScores,
+    /// Outcomes of the saves, errors are reported on stderr.
+    messages: Vec<Result<String, String>>,
+}
+
+impl Saves {
+    /// Writes the replay of the game to the `--record` file.
+    fn save_replay(&mut self, recorder: &Recorder, options: &Options) {
+        if let Some(path) = &options.record {
+            let saved = recorder.replay().save(path).map(|()| format!("replay saved to {}", path.display()));
+            self.messages.push(saved);
+        }
+    }
+
+    /// Saves the replay of the ended game and its personal best, returns its leaderboard rank when it qualifies.
+    fn end_game(&mut self, state: &GameState, recorder: &Recorder, options: &Options) -> Option<usize> {
+        self.save_replay(recorder, options);
+        if self.personal_bests.submit(state) {
+            let record = personal_best(state, &self.personal_bests).map_or(0, |record| record.frames);
+            let saved = self.personal_bests.save().map(|()| format!("new personal best: {}", tetris_rs::mode::format_time(record)));
+            self.messages.push(saved);
+        }
+        self.high_scores.rank(state)
+    }
+
+    fn insert_high_score(&mut self, state: &GameState, name: &str) {
+        self.high_scores.insert(state, name);
+        if let Err(e) = self.high_scores.save() {
+            self.messages.push(Err(e));
+        }
+    }
+}
+
+/// Record a Sprint is compared against.
+fn personal_best(state: &GameState, personal_bests: &PersonalBests) -> Option<SprintRecord> {
+    match state.settings().mode {
+        GameMode::Sprint { lines } => personal_bests.sprint(lines).cloned(),
+        GameMode::Endless | GameMode::Ultra => None,
+    }
+}
+
+/// Plays games until the player quits, the restart key abandons the game and starts a new one with a random seed.
+fn run(state: &mut GameState, recorder: &mut Recorder, saves: &mut Saves, options: &Options, terminal: &Terminal) -> io::Result<()> {
+    let mut personal_best = personal_best(state, &saves.personal_bests);
+    // keys pressed in terminals without release events are released after the following frame
+    let mut tapped: Vec<Action> = Vec::new();
+    let keys = &options.keys;
+    let end_hint = format!("{} - retry   L - leaderboard   {} - quit", key_name(keys, Control::Restart), key_name(keys, Control::Quit));
+    let mut renderer = render::Renderer::new(end_hint);
+    let mut ending = Ending::None;
+    let mut end_handled = false;
+
+    run_frames(options.display.fps, |tick, stdout| {
+        match (tick, &mut ending) {
+            (Tick::Key(_, KeyEventKind::Release), Ending::NameEntry { .. }) => {},
+            (Tick::Key(code, _), Ending::NameEntry { name, rank }) => match code {
+                KeyCode::Backspace => {
+                    name.pop();
+                },
+                KeyCode::Enter => {
+                    saves.insert_high_score(state, name);
+                    ending = Ending::Leaderboard { highlight: Some(*rank) };
+                },
+                KeyCode::Esc => ending = Ending::None,
+                KeyCode::Char(character) if name.chars().count() < HighScores::MAX_NAME_LENGTH => name.push(character),
+                _ => {}
+            },
+            (Tick::Key(code, KeyEventKind::Press), _) if is_quit(keys, code) || state.has_ended() && code == KeyCode::Esc => return Ok(false),
+            (Tick::Key(code, KeyEventKind::Press), _) if control(keys, code) == Some(Control::Restart) => {
+                // an ended game was saved when it ended
+                if !end_handled {
+                    saves.save_replay(recorder, options);
+                }
+                *state = GameState::new(rand::random(), *state.settings());
+                *recorder = Recorder::new(state);
+                personal_best = self::personal_best(state, &saves.personal_bests);
+                tapped.clear();
+                ending = Ending::None;
+                end_handled = false;
+            },
+            (Tick::Key(KeyCode::Char('l'), KeyEventKind::Press), Ending::Leaderboard { .. }) => ending = Ending::None,
+            (Tick::Key(KeyCode::Char('l'), KeyEventKind::Press), Ending::None) if state.has_ended() => {
+                ending = Ending::Leaderboard { highlight: None };
+            },
+            (Tick::Key(code, kind), _) => {
+                if let Some(Control::Action(action)) = control(keys, code) {
+                    match kind {
+                        KeyEventKind::Press | KeyEventKind::Repeat => recorder.key_down(state, action),
+                        KeyEventKind::Release => recorder.key_up(state, action),
+                    }
+                    if !terminal.key_release_events {
+                        tapped.push(action);
+                    }
+                }
+            },
+            (Tick::Frame, _) => {
+                recorder.update(state);
+                for action in tapped.drain(..) {
+                    recorder.key_up(state, action);
+                }
+                if state.has_ended() && !end_handled {
+                    end_handled = true;
+                    if let Some(rank) = saves.end_game(state, recorder, options) {
+                        ending = Ending::NameEntry { name: player_name(), rank };
+                    }
+                }
+            },
+            (Tick::Draw, _) => {
+                match &ending {
+                    Ending::None => renderer.draw_game(state, personal_best.as_ref(), stdout)?,
+                    Ending::NameEntry { name, rank } => renderer.draw_screen(render::name_entry_lines(name, *rank), stdout)?,
+                    Ending::Leaderboard { highlight } => {
+                        let mode = state.settings().mode;
+                        renderer.draw_screen(render::leaderboard_lines(mode, saves.high_scores.table(mode), *highlight), stdout)?
+                    },
+                }
+            },
+        }
+        Ok(true)
+    })
+}
+
+fn run_replay(playback: &mut Playback, options: &Options) -> io::Result<()> {
+    let mut renderer = render::Renderer::new(format!("Esc / {} - quit", key_name(&options.keys, Control::Quit)));
+
+    run_frames(options.display.fps, |tick, stdout| {
+        match tick {
+            Tick::Key(_, KeyEventKind::Release) => {},
+            Tick::Key(code, _) if code == KeyCode::Esc || is_quit(&options.keys, code) => return Ok(false),
+            Tick::Key(code, _) => match code {
+                KeyCode::Char(' ') | KeyCode::Char('p') => playback.toggle_pause(),
+                KeyCode::Right | KeyCode::Char('.') => playback.step(),
+                KeyCode::Up => playback.faster(),
+                KeyCode::Down => playback.slower(),
+                KeyCode::Left => playback.seek(playback.frame().saturating_sub(SEEK_FRAMES)),
+                KeyCode::Home => playback.seek(0),
+                _ => {}
+            },
+            Tick::Frame => playback.update(),
+            Tick::Draw => renderer.draw_playback(playback, stdout)?,
+        }
+        Ok(true)
+    })
+}
+
+/// First key bound to the control, as shown in hints.
+fn key_name(keys: &KeyBindings, control: Control) -> String {
+    keys.inputs(control).first().map_or("-".to_string(), |key| key.to_string())
+}
+
+/// Name suggested on the name entry screen.
+fn player_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .map(|name| name.chars().take(HighScores::MAX_NAME_LENGTH).collect())
+        .unwrap_or_default()
+}
+
+fn main() -> Result<(), String> {
+    let options = Options::parse(std::env::args().skip(1))?;
+    if let Some(path) = &options.replay {
+        let mut playback = Playback::new(Replay::load(path)?);
+        let _terminal = Terminal::enter().map_err(|e| e.to_string())?;
+        return run_replay(&mut playback, &options).map_err(|e| e.to_string());
+    }
+
+    let mut state = GameState::new(options.seed, options.settings);
+    let mut recorder = Recorder::new(&state);
+    let mut saves = Saves {
+        personal_bests: PersonalBests::load().unwrap_or_else(|e| {
+            eprintln!("{}, personal bests are reset", e);
+            PersonalBests::default()
+        }),
+        high_scores: HighScores::load().unwrap_or_else(|e| {
+            eprintln!("{}, high scores are reset", e);
+            HighScores::default()
+        }),
+        messages: Vec::new(),
+    };
+    {
+        let terminal = Terminal::enter().map_err(|e| e.to_string())?;
+        run(&mut state, &mut recorder, &mut saves, &options, &terminal).map_err(|e| e.to_string())?;
+    }
+    // an ended game was saved when it ended
+    if !state.has_ended() {
+        saves.save_replay(&recorder, &options);
+    }
+    println!("seed: {}", state.seed());
+    for message in saves.messages {
+        match message {
+            Ok(message) => println!("{}", message),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,373 @@
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+};
+use tetris_rs::{board_tile::BoardTile, delays::ClearingTile, high_scores::HighScore, mode::{self, GameMode}, personal_best::SprintRecord, replay::Playback, tetromino::TetrominoKind, GameState};
+
+const PANEL_WIDTH: usize = 8;
+const PANEL_COLOR: Color = Color::Rgb { r: 30, g: 30, b: 30 };
+const GHOST_COLOR: Color = Color::DarkGrey;
+const BOARD_LEFT: usize = PANEL_WIDTH;
+/// Number of frames a line clear callout stays on the screen.
+const CALLOUT_FRAMES: u64 = 90;
+/// Rows of the hidden buffer shown above the board (one line of the terminal), where only tetrominoes are drawn so
+/// that spawning ones peek out.
+const BUFFER_ROWS: usize = 2;
+
+/// Grid of board-sized cells, every terminal character shows two of them stacked with a half block.
+struct Pixels {
+    width: usize,
+    height: usize,
+    colors: Vec<Color>,
+}
+
+impl Pixels {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height: height + height % 2,
+            colors: vec![Color::Black; width * (height + height % 2)],
+        }
+    }
+
+    fn set(&mut self, x: i32, y: i32, color: Color) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.colors[y as usize * self.width + x as usize] = color;
+        }
+    }
+
+    fn fill(&mut self, left: usize, width: usize, color: Color) {
+        for y in 0..self.height {
+            for x in left..left + width {
+                self.set(x as i32, y as i32, color);
+            }
+        }
+    }
+
+    /// Writes the characters that differ from `previous` (everything when its size differs).
+    fn render(&self, previous: Option<&Pixels>, out: &mut impl Write) -> io::Result<()> {
+        let previous = previous.filter(|previous| previous.width == self.width && previous.height == self.height);
+        for row in 0..self.height / 2 {
+            for x in 0..self.width {
+                let upper_index = 2 * row * self.width + x;
+                let lower_index = upper_index + self.width;
+                let upper = self.colors[upper_index];
+                let lower = self.colors[lower_index];
+                if let Some(previous) = previous {
+                    if previous.colors[upper_index] == upper && previous.colors[lower_index] == lower {
+                        continue;
+                    }
+                }
+                // `Color::Reset` cells keep the background of the terminal, only a lower half block can be drawn over it
+                let (foreground, background, block) = match (upper, lower) {
+                    (Color::Reset, Color::Reset) => (Color::Reset, Color::Reset, ' '),
+                    (Color::Reset, lower) => (lower, Color::Reset, '▄'),
+                    (upper, lower) => (upper, lower, '▀'),
+                };
+                queue!(out, cursor::MoveTo(x as u16, row as u16), SetForegroundColor(foreground), SetBackgroundColor(background), Print(block))?;
+            }
+        }
+        queue!(out, ResetColor)
+    }
+}
+
+/// Draws the game, only sending the changes since the previous frame to the terminal.
+pub struct Renderer {
+    previous: Option<Pixels>,
+    previous_info: Vec<String>,
+    /// Text drawn by `draw_screen`, empty while the game is drawn.
+    previous_screen: Vec<String>,
+    /// Keys listed on the end screen.
+    end_hint: String,
+}
+
+impl Renderer {
+    pub fn new(end_hint: String) -> Self {
+        Self {
+            previous: None,
+            previous_info: Vec::new(),
+            previous_screen: Vec::new(),
+            end_hint,
+        }
+    }
+
+    /// Draws the game, `personal_best` is the record a Sprint is compared against.
+    pub fn draw_game(&mut self, state: &GameState, personal_best: Option<&SprintRecord>, out: &mut impl Write) -> io::Result<()> {
+        if state.has_ended() {
+            let mut lines = end_lines(state, personal_best);
+            lines.push(String::new());
+            lines.push(self.end_hint.clone());
+            return self.draw_screen(lines, out);
+        }
+
+        let pixels = draw_pixels(state);
+        if self.previous.is_none() {
+            queue!(out, crossterm::terminal::Clear(crossterm::terminal::ClearType::All))?;
+            self.previous_info.clear();
+            self.previous_screen.clear();
+        }
+        pixels.render(self.previous.as_ref(), out)?;
+        print_at(out, 2, 0, "HOLD", PANEL_COLOR)?;
+        print_at(out, next_left(state) + 2, 0, "NEXT", PANEL_COLOR)?;
+        self.previous = Some(pixels);
+
+        let info = info_lines(state, personal_best);
+        for (row, line) in info.iter().enumerate() {
+            if self.previous_info.get(row) != Some(line) {
+                print_at(out, info_left(state), row, &format!("{:<16}", line), Color::Reset)?;
+            }
+        }
+        self.previous_info = info;
+        Ok(())
+    }
+
+    /// Replaces the screen with lines of text, nothing is sent when the same text is already shown.
+    pub fn draw_screen(&mut self, lines: Vec<String>, out: &mut impl Write) -> io::Result<()> {
+        if self.previous.is_none() && self.previous_screen == lines {
+            return Ok(());
+        }
+        self.previous = None;
+        queue!(out, ResetColor, crossterm::terminal::Clear(crossterm::terminal::ClearType::All))?;
+        for (row, line) in lines.iter().enumerate() {
+            print_at(out, 0, row, line, Color::Reset)?;
+        }
+        self.previous_screen = lines;
+        Ok(())
+    }
+
+    /// Draws the replayed game with the playback position and speed below the game info.
+    pub fn draw_playback(&mut self, playback: &Playback, out: &mut impl Write) -> io::Result<()> {
+        self.draw_game(playback.state(), None, out)?;
+        let status = if playback.is_paused() { "PAUSED".to_string() } else { format!("x{}", playback.speed()) };
+        let lines = [
+            "REPLAY".to_string(),
+            format!("{} / {}", format_frames(playback.frame()), format_frames(playback.length())),
+            status,
+        ];
+        let state = playback.state();
+        let left = if state.has_ended() { 24 } else { info_left(state) };
+        for (row, line) in lines.iter().enumerate() {
+            print_at(out, left, state.height() / 2 + row, &format!("{:<16}", line), Color::Reset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a number of frames as minutes and seconds.
+fn format_frames(frames: u64) -> String {
+    let seconds = frames / tetris_rs::FRAMES_PER_SECOND;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Column the next queue panel starts at, right of the board.
+fn next_left(state: &GameState) -> usize {
+    BOARD_LEFT + state.width()
+}
+
+/// Column the game info starts at, right of the next queue panel.
+fn info_left(state: &GameState) -> usize {
+    next_left(state) + PANEL_WIDTH + 2
+}
+
+fn draw_pixels(state: &GameState) -> Pixels {
+    let next_queue = state.next_queue();
+    let next_left = next_left(state);
+    let height = (BUFFER_ROWS + state.height()).max(2 + 3 * next_queue.len());
+    let mut pixels = Pixels::new(next_left + PANEL_WIDTH, height);
+    pixels.fill(0, PANEL_WIDTH, PANEL_COLOR);
+    pixels.fill(next_left, PANEL_WIDTH, PANEL_COLOR);
+    for y in 0..BUFFER_ROWS {
+        for x in BOARD_LEFT..next_left {
+            pixels.set(x as i32, y as i32, Color::Reset);
+        }
+    }
+
+    for tile in state.board() {
+        let seg = match state.to_view(tile, BUFFER_ROWS) {
+            Some(seg) => seg,
+            None => continue,
+        };
+        let color = match seg.kind {
+            Some(_) if state.clearing_rows().contains(&(tile.y as usize)) => clearing_color(state, &seg),
+            Some(kind) => Some(kind_color(kind)),
+            None => None,
+        };
+        if let Some(color) = color {
+            pixels.set((BOARD_LEFT as u32 + seg.x) as i32, seg.y as i32, color);
+        }
+    }
+    if let Some(ghost) = state.ghost() {
+        for tile in ghost.tiles().iter().filter_map(|tile| state.to_view(tile, BUFFER_ROWS)) {
+            pixels.set((BOARD_LEFT as u32 + tile.x) as i32, tile.y as i32, GHOST_COLOR);
+        }
+    }
+    for tile in state.tetromino().map_or(Vec::new(), |tetromino| tetromino.tiles()).iter().filter_map(|tile| state.to_view(tile, BUFFER_ROWS)) {
+        pixels.set((BOARD_LEFT as u32 + tile.x) as i32, tile.y as i32, kind_color(tile.kind.unwrap()));
+    }
+    for tile in state.lock_flash().map_or(Vec::new(), |tetromino| tetromino.tiles()).iter().filter_map(|tile| state.to_view(tile, BUFFER_ROWS)) {
+        pixels.set((BOARD_LEFT as u32 + tile.x) as i32, tile.y as i32, Color::White);
+    }
+
+    if let Some(kind) = state.held() {
+        let color = if state.can_hold() { kind_color(kind) } else { GHOST_COLOR };
+        draw_preview(&mut pixels, &state.preview(kind), color, 0, 2);
+    }
+    for (i, kind) in next_queue.iter().enumerate() {
+        draw_preview(&mut pixels, &state.preview(*kind), kind_color(*kind), next_left, 2 + 3 * i);
+    }
+
+    pixels
+}
+
+fn info_lines(state: &GameState, personal_best: Option<&SprintRecord>) -> Vec<String> {
+    let score = state.score();
+    let mut lines = match state.settings().mode {
+        GameMode::Endless => vec![
+            format!("SCORE {}", score.points()),
+            format!("LEVEL {}", state.level()),
+            format!("LINES {}", score.lines()),
+        ],
+        GameMode::Ultra => vec![
+            format!("TIME  {}", mode::format_time(GameMode::ULTRA_FRAMES.saturating_sub(state.frame()))),
+            format!("SCORE {}", score.points()),
+            format!("LINES {}", score.lines()),
+        ],
+        GameMode::Sprint { lines } => vec![
+            format!("TIME  {}", mode::format_time(state.frame())),
+            format!("LINES {}/{}", score.lines(), lines),
+            format!("SPLIT {}", personal_best.and_then(|record| record.delta(state.splits())).map_or("-".to_string(), mode::format_delta)),
+        ],
+    };
+    lines.push(String::new());
+    if let Some(event) = state.last_lock().filter(|event| state.frame() - event.frame < CALLOUT_FRAMES) {
+        if event.back_to_back {
+            lines.push("BACK-TO-BACK".to_string());
+        }
+        lines.extend(event.clear.name());
+        if let Some(combo) = event.combo.filter(|&combo| combo > 0) {
+            lines.push(format!("{} COMBO", combo));
+        }
+        if event.clear.perfect_clear {
+            lines.push("PERFECT CLEAR".to_string());
+        }
+    }
+    if state.is_paused() {
+        lines.push("PAUSED".to_string());
+    }
+    lines.resize(state.height() / 2, String::new());
+    lines
+}
+
+/// Text of the screen shown when the game has ended.
+fn end_lines(state: &GameState, personal_best: Option<&SprintRecord>) -> Vec<String> {
+    if state.is_finished() {
+        let best = match personal_best {
+            Some(record) if record.frames <= state.frame() => format!("PB   {}", mode::format_time(record.frames)),
+            _ => "NEW PERSONAL BEST".to_string(),
+        };
+        vec!["FINISHED".to_string(), format!("TIME {}", mode::format_time(state.frame())), best]
+    } else if state.is_timed_out() {
+        let mut lines = vec!["TIME UP".to_string(), String::new()];
+        lines.extend(result_lines(state));
+        lines
+    } else {
+        let reason = state.game_over_reason().map_or("", |reason| reason.name());
+        vec!["GAME OVER".to_string(), reason.to_string(), format!("SCORE {}", state.score().points())]
+    }
+}
+
+/// Asks for the name to put on the leaderboard.
+pub fn name_entry_lines(name: &str, rank: usize) -> Vec<String> {
+    vec![
+        format!("HIGH SCORE #{}", rank + 1),
+        String::new(),
+        "ENTER YOUR NAME".to_string(),
+        format!("{}_", name),
+        String::new(),
+        "Enter - confirm   Esc - skip".to_string(),
+    ]
+}
+
+/// Lists the high scores of the mode, marking the entry at `highlight`.
+pub fn leaderboard_lines(mode: GameMode, entries: &[HighScore], highlight: Option<usize>) -> Vec<String> {
+    let row = |rank: &str, columns: &[String]| {
+        format!("{:>3} {:<12} {:>8} {:>5} {:>3} {:>9} {}", rank, columns[0], columns[1], columns[2], columns[3], columns[4], columns[5])
+    };
+    let titles: Vec<String> = HighScore::COLUMN_TITLES.iter().map(|title| title.to_string()).collect();
+    let mut lines = vec![format!("{} - HIGH SCORES", mode.name().to_uppercase()), String::new(), row("#", &titles)];
+    for (rank, entry) in entries.iter().enumerate() {
+        let marker = if highlight == Some(rank) { ">" } else { "" };
+        lines.push(row(&format!("{}{}.", marker, rank + 1), &entry.columns()));
+    }
+    if entries.is_empty() {
+        lines.push("no entries".to_string());
+    }
+    lines.push(String::new());
+    lines.push("L - back   Esc - quit".to_string());
+    lines
+}
+
+/// Score, line and piece counts and the breakdown of clears of a finished game.
+fn result_lines(state: &GameState) -> Vec<String> {
+    let statistics = state.statistics();
+    let mut lines = vec![
+        format!("SCORE  {}", state.score().points()),
+        format!("LINES  {}", state.score().lines()),
+        format!("PIECES {}", statistics.pieces()),
+        format!("PPS    {:.2}", statistics.pieces_per_second(state.frame())),
+        String::new(),
+    ];
+    lines.extend(statistics.breakdown().into_iter().map(|(name, count)| format!("{:<19}{}", name, count)));
+    lines
+}
+
+fn print_at(out: &mut impl Write, x: usize, y: usize, text: &str, background: Color) -> io::Result<()> {
+    queue!(
+        out,
+        cursor::MoveTo(x as u16, y as u16),
+        SetForegroundColor(Color::White),
+        SetBackgroundColor(background),
+        Print(text),
+        ResetColor
+    )
+}
+
+/// Draws the tetromino shape at `top`, centered in the panel starting at `left`, skipping its empty rows.
+fn draw_preview(pixels: &mut Pixels, shape: &[Vec<bool>], color: Color, left: usize, top: usize) {
+    let rows: Vec<&Vec<bool>> = shape.iter().filter(|row| row.iter().any(|x| *x)).collect();
+    let columns = (0..shape[0].len()).filter(|&x| rows.iter().any(|row| row[x])).collect::<Vec<usize>>();
+    let left = left + (PANEL_WIDTH - columns.len()) / 2;
+    for (y, row) in rows.iter().enumerate() {
+        for (x, column) in columns.iter().enumerate() {
+            if row[*column] {
+                pixels.set((left + x) as i32, (top + y) as i32, color);
+            }
+        }
+    }
+}
+
+/// Color of a tile of a row being cleared at the current frame of the line clear animation, `None` once it is gone.
+fn clearing_color(state: &GameState, tile: &BoardTile) -> Option<Color> {
+    let delays = state.settings().delays;
+    match delays.animation.tile(tile.x as usize, state.width(), state.phase_frames(), delays.line_clear) {
+        ClearingTile::White => Some(Color::White),
+        // terminals have few colors, fading goes through grey
+        ClearingTile::Faded(opacity) if opacity > 0.67 => tile.kind.map(kind_color),
+        ClearingTile::Faded(opacity) if opacity > 0.33 => Some(GHOST_COLOR),
+        ClearingTile::Faded(_) => None,
+    }
+}
+
+fn kind_color(kind: TetrominoKind) -> Color {
+    match kind {
+        TetrominoKind::I => Color::Cyan,
+        TetrominoKind::O => Color::Yellow,
+        TetrominoKind::T => Color::Rgb { r: 0xa0, g: 0x20, b: 0xf0 },
+        TetrominoKind::S => Color::Green,
+        TetrominoKind::Z => Color::Red,
+        TetrominoKind::J => Color::Blue,
+        TetrominoKind::L => Color::Rgb { r: 0xff, g: 0xa5, b: 0x00 },
+    }
+}
@@ -1,11 +1,10 @@
-use ggez::graphics::{self, Color}
-;
+use crate::tetromino::TetrominoKind;
 
 #[derive(Copy, Clone)]
 pub struct BoardTile {
     pub x: u32,
     pub y: u32, 
-    pub color: Color
+    pub kind: Option<TetrominoKind>
 }
 
 impl BoardTile {
@@ -14,26 +13,19 @@ impl BoardTile {
         Self {
             x: 0,
             y: 0,
-            color: Color::BLACK,
+            kind: None,
         }
     }
 
-    pub fn new(x: u32, y: u32, color: Color) -> Self {
+    pub fn new(x: u32, y: u32, kind: TetrominoKind) -> Self {
         Self {
             x,
             y,
-            color
+            kind: Some(kind)
         }
     }
-}
 
-impl From<BoardTile> for graphics::Rect {
-    fn from(item: BoardTile) -> Self {
-        graphics::Rect::new_i32(
-            item.x as i32 * crate::TILE_SIZE as i32,
-            item.y as i32 * crate::TILE_SIZE as i32,
-            crate::TILE_SIZE as i32,
-            crate::TILE_SIZE as i32,
-        )
+    pub fn is_empty(&self) -> bool {
+        self.kind.is_none()
     }
-}
\ No newline at end of file
+}
@@ -1,18 +1,14 @@
-use std::time::{Duration, Instant};
-
 pub struct ButtonState {
-    pressed_duration: Option<Instant>,
+    pressed_frames: Option<u32>,
     handled: bool,
     key_down_was_noticed: bool
 }
 
 impl ButtonState {
 
-    const HOLD_DURATION_MILLIS: u64 = 250;
-
     pub fn new() -> Self {
         Self {
-            pressed_duration: None,
+            pressed_frames: None,
             handled: false,
             key_down_was_noticed: false
         }
@@ -22,43 +18,44 @@ impl ButtonState {
         if self.key_down_was_noticed {
             return;
         }
-        self.pressed_duration = Some(Instant::now());
+        self.pressed_frames = Some(0);
         self.key_down_was_noticed = true;
     }
 
     pub fn key_up(&mut self) {
-        self.pressed_duration = None;
+        self.pressed_frames = None;
         self.handled = false;
         self.key_down_was_noticed = false;
     }
 
-    pub fn should_handle_once(&self) -> bool {
-        self.key_down_was_noticed && ! self.handled
+    /// Advances the press timer by one frame, should be called once at the end of every game frame.
+    pub fn tick(&mut self) {
+        if let Some(frames) = self.pressed_frames.as_mut() {
+            *frames = frames.saturating_add(1);
+        }
     }
 
-    pub fn handled_once(&mut self) {
-        self.handled = true;
+    /// Number of frames the button has been held for, `None` when it is released.
+    pub fn pressed_frames(&self) -> Option<u32> {
+        self.pressed_frames
     }
 
-    #[allow(dead_code)]
-    pub fn is_short_pressed(&self) -> bool {
-        match self.pressed_duration {
-            Some(duration) => duration + Duration::from_millis(Self::HOLD_DURATION_MILLIS) > Instant::now(),
-            None => false
-        }
+    pub fn should_handle_once(&self) -> bool {
+        self.key_down_was_noticed && ! self.handled
     }
 
-    #[allow(dead_code)]
-    pub fn is_long_pressed(&self) -> bool {
-        match self.pressed_duration {
-            Some(duration) => duration + Duration::from_millis(Self::HOLD_DURATION_MILLIS) <= Instant::now(),
-            None => false
-        }
+    pub fn handled_once(&mut self) {
+        self.handled = true;
     }
 
-    #[allow(dead_code)]
     pub fn is_pressed(&self) -> bool {
         self.key_down_was_noticed
     }
 
-}
\ No newline at end of file
+}
+
+impl Default for ButtonState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,139 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{controls::{ButtonBindings, KeyBindings}, settings::GameSettings, storage};
+
+/// Contents of the configuration file, every value is optional and falls back to its default.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Rules of new games, including the board size and the handling timings.
+    pub game: GameSettings,
+    pub display: Display,
+    pub keys: KeyBindings,
+    pub gamepad: Gamepad,
+}
+
+/// Presentation of the game, it does not affect the rules.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Display {
+    /// Width (and height) of one board tile in pixels, used by the windowed front end.
+    pub tile_size: u32,
+    /// Width of each of the side panels next to the board in tiles, used by the windowed front end.
+    pub side_panel_width: u32,
+    /// Highest number of times per second the screen is redrawn. The game logic always runs at
+    /// `FRAMES_PER_SECOND` updates per second, whatever the redraw rate.
+    pub fps: u32,
+}
+
+impl Display {
+    pub const MIN_TILE_SIZE: u32 = 8;
+    pub const MAX_TILE_SIZE: u32 = 100;
+    pub const MIN_SIDE_PANEL_WIDTH: u32 = 4;
+    pub const MAX_SIDE_PANEL_WIDTH: u32 = 20;
+    pub const MIN_FPS: u32 = 1;
+    pub const MAX_FPS: u32 = 1000;
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(Self::MIN_TILE_SIZE..=Self::MAX_TILE_SIZE).contains(&self.tile_size) {
+            return Err(format!("tile_size must be from {} to {}, got {}", Self::MIN_TILE_SIZE, Self::MAX_TILE_SIZE, self.tile_size));
+        }
+        if !(Self::MIN_SIDE_PANEL_WIDTH..=Self::MAX_SIDE_PANEL_WIDTH).contains(&self.side_panel_width) {
+            return Err(format!("side_panel_width must be from {} to {}, got {}", Self::MIN_SIDE_PANEL_WIDTH, Self::MAX_SIDE_PANEL_WIDTH, self.side_panel_width));
+        }
+        if !(Self::MIN_FPS..=Self::MAX_FPS).contains(&self.fps) {
+            return Err(format!("fps must be from {} to {}, got {}", Self::MIN_FPS, Self::MAX_FPS, self.fps));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self {
+            tile_size: 20,
+            side_panel_width: 6,
+            fps: crate::FRAMES_PER_SECOND as u32,
+        }
+    }
+}
+
+/// Gamepad input of the windowed front end; every connected pad uses the same bindings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Gamepad {
+    /// How far a stick, or a D-pad reported as axes, has to be pushed for its direction to count as pressed,
+    /// as a fraction of the full range.
+    pub deadzone: f32,
+    pub buttons: ButtonBindings,
+}
+
+impl Gamepad {
+    pub const MIN_DEADZONE: f32 = 0.05;
+    pub const MAX_DEADZONE: f32 = 0.95;
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(Self::MIN_DEADZONE..=Self::MAX_DEADZONE).contains(&self.deadzone) {
+            return Err(format!("deadzone must be from {} to {}, got {}", Self::MIN_DEADZONE, Self::MAX_DEADZONE, self.deadzone));
+        }
+        self.buttons.validate().map_err(|e| format!("buttons: {}", e))
+    }
+}
+
+impl Default for Gamepad {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.5,
+            buttons: ButtonBindings::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Path of `config.toml` in the user's configuration directory, e.g. `~/.config/tetris-rs` on Linux.
+    pub fn default_path() -> Result<PathBuf, String> {
+        storage::config_path("config.toml")
+    }
+
+    /// Reads the file at `default_path`, without the file the defaults are used.
+    pub fn load_default() -> Result<Self, String> {
+        match Self::default_path() {
+            Ok(path) if path.exists() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Replaces the key and gamepad button bindings in the configuration file, keeping its other values (but not its comments).
+    pub fn save_bindings(path: &Path, keys: &KeyBindings, buttons: &ButtonBindings) -> Result<(), String> {
+        let mut document = match fs::read_to_string(path) {
+            Ok(content) => content.parse::<toml::Value>().map_err(|e| format!("invalid config file {}: {}", path.display(), e))?,
+            Err(_) => toml::Value::Table(Default::default()),
+        };
+        let keys = toml::Value::try_from(keys).map_err(|e| format!("can not serialize key bindings: {}", e))?;
+        let buttons = toml::Value::try_from(buttons).map_err(|e| format!("can not serialize button bindings: {}", e))?;
+        let table = document.as_table_mut().expect("a TOML document is a table");
+        table.insert("keys".to_string(), keys);
+        match table.entry("gamepad").or_insert_with(|| toml::Value::Table(Default::default())) {
+            toml::Value::Table(gamepad) => gamepad.insert("buttons".to_string(), buttons),
+            _ => return Err(format!("invalid config file {}: gamepad must be a table", path.display())),
+        };
+        storage::save_toml(path, &document)
+    }
+
+    /// Reads and validates a configuration file; unlike saved data it is never moved aside, as it is written by hand.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("can not read {}: {}", path.display(), e))?;
+        let config: Config = toml::from_str(&content).map_err(|e| format!("invalid config file {}: {}", path.display(), e))?;
+        config.validate().map_err(|e| format!("invalid config file {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.game.validate().map_err(|e| format!("game.{}", e))?;
+        self.display.validate().map_err(|e| format!("display.{}", e))?;
+        self.keys.validate().map_err(|e| format!("keys: {}", e))?;
+        self.gamepad.validate().map_err(|e| format!("gamepad.{}", e))
+    }
+}
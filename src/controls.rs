@@ -0,0 +1,471 @@
+use std::{collections::BTreeMap, fmt, marker::PhantomData, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::action::Action;
+
+/// Key of a keyboard, independent of the front end; each front end translates its own key codes.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Key {
+    /// Letter (uppercase), digit or punctuation key.
+    Char(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    Space,
+    Enter,
+    Escape,
+    Backspace,
+    Tab,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    LShift,
+    RShift,
+    LControl,
+    RControl,
+    LAlt,
+    RAlt,
+    /// Numeric keypad digit.
+    Numpad(u8),
+    /// Function key, F1 to F24.
+    F(u8),
+}
+
+impl Key {
+    const NAMED: [(Key, &'static str); 21] = [
+        (Key::Left, "Left"),
+        (Key::Right, "Right"),
+        (Key::Up, "Up"),
+        (Key::Down, "Down"),
+        (Key::Space, "Space"),
+        (Key::Enter, "Enter"),
+        (Key::Escape, "Escape"),
+        (Key::Backspace, "Backspace"),
+        (Key::Tab, "Tab"),
+        (Key::Insert, "Insert"),
+        (Key::Delete, "Delete"),
+        (Key::Home, "Home"),
+        (Key::End, "End"),
+        (Key::PageUp, "PageUp"),
+        (Key::PageDown, "PageDown"),
+        (Key::LShift, "LShift"),
+        (Key::RShift, "RShift"),
+        (Key::LControl, "LControl"),
+        (Key::RControl, "RControl"),
+        (Key::LAlt, "LAlt"),
+        (Key::RAlt, "RAlt"),
+    ];
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::Numpad(digit) => write!(f, "Numpad{}", digit),
+            Key::F(number) => write!(f, "F{}", number),
+            key => {
+                let (_, name) = Self::NAMED.iter().find(|(named, _)| named == key).unwrap();
+                write!(f, "{}", name)
+            },
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// Parses names as printed by `Display`, letters in either case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii_graphic() {
+                return Ok(Key::Char(c.to_ascii_uppercase()));
+            }
+        }
+        if let Some((key, _)) = Self::NAMED.iter().find(|(_, name)| name.eq_ignore_ascii_case(s)) {
+            return Ok(*key);
+        }
+        let numbered = |prefix: &str, max: u8| s.strip_prefix(prefix)
+            .and_then(|number| number.parse::<u8>().ok())
+            .filter(|number| *number <= max);
+        if let Some(digit) = numbered("Numpad", 9) {
+            return Ok(Key::Numpad(digit));
+        }
+        match numbered("F", 24) {
+            Some(number) if number >= 1 => Ok(Key::F(number)),
+            _ => Err(format!("unknown key '{}', expected a letter, a digit, a punctuation character, F1-F24, Numpad0-Numpad9 or one of: {}",
+                s, Self::NAMED.iter().map(|(_, name)| *name).collect::<Vec<&str>>().join(", "))),
+        }
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Button of a gamepad, independent of the front end; directions of the sticks, and of D-pads reported as axes,
+/// count as buttons pressed while the axis is past the deadzone.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Button {
+    /// Bottom face button: A on Xbox pads, cross on PlayStation ones, B on Nintendo ones.
+    South,
+    East,
+    North,
+    West,
+    LeftTrigger,
+    LeftTrigger2,
+    RightTrigger,
+    RightTrigger2,
+    Select,
+    Start,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftStickUp,
+    LeftStickDown,
+    LeftStickLeft,
+    LeftStickRight,
+    RightStickUp,
+    RightStickDown,
+    RightStickLeft,
+    RightStickRight,
+}
+
+impl Button {
+    pub const ALL: [Button; 24] = [
+        Button::South,
+        Button::East,
+        Button::North,
+        Button::West,
+        Button::LeftTrigger,
+        Button::LeftTrigger2,
+        Button::RightTrigger,
+        Button::RightTrigger2,
+        Button::Select,
+        Button::Start,
+        Button::LeftThumb,
+        Button::RightThumb,
+        Button::DPadUp,
+        Button::DPadDown,
+        Button::DPadLeft,
+        Button::DPadRight,
+        Button::LeftStickUp,
+        Button::LeftStickDown,
+        Button::LeftStickLeft,
+        Button::LeftStickRight,
+        Button::RightStickUp,
+        Button::RightStickDown,
+        Button::RightStickLeft,
+        Button::RightStickRight,
+    ];
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Button {
+    type Err = String;
+
+    /// Parses names as printed by `Display`, in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|button| button.to_string().eq_ignore_ascii_case(s)).ok_or_else(|| {
+            format!("unknown button '{}', expected one of: {}", s, Self::ALL.map(|button| button.to_string()).join(", "))
+        })
+    }
+}
+
+impl Serialize for Button {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Button {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// What a key or a gamepad button can be bound to: an action of the game or a command of the front end.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Control {
+    Action(Action),
+    /// Abandons the game and starts a new one with the same settings.
+    Restart,
+    Quit,
+}
+
+impl Control {
+    /// Every control, in the order they are listed on the controls screen.
+    pub const ALL: [Control; 11] = [
+        Control::Action(Action::MoveLeft),
+        Control::Action(Action::MoveRight),
+        Control::Action(Action::RotateClockwise),
+        Control::Action(Action::RotateCounterClockwise),
+        Control::Action(Action::Rotate180),
+        Control::Action(Action::SoftDrop),
+        Control::Action(Action::HardDrop),
+        Control::Action(Action::Hold),
+        Control::Action(Action::Pause),
+        Control::Restart,
+        Control::Quit,
+    ];
+
+    /// Name of the control in the key and button bindings of the configuration file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::Action(action) => action.name(),
+            Control::Restart => "restart",
+            Control::Quit => "quit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|control| control.name() == name)
+    }
+}
+
+/// A key or a gamepad button, what controls are bound to.
+pub trait Input: Copy + Eq + fmt::Display + FromStr<Err = String> + Serialize + for<'de> Deserialize<'de> {
+    /// What the input is called in messages.
+    const KIND: &'static str;
+
+    fn defaults(control: Control) -> Vec<Self>;
+}
+
+impl Input for Key {
+    const KIND: &'static str = "key";
+
+    fn defaults(control: Control) -> Vec<Self> {
+        match control {
+            Control::Action(Action::MoveLeft) => vec![Key::Left],
+            Control::Action(Action::MoveRight) => vec![Key::Right],
+            Control::Action(Action::RotateClockwise) => vec![Key::Up, Key::Char('X')],
+            Control::Action(Action::RotateCounterClockwise) => vec![Key::Numpad(0), Key::Char('Z')],
+            Control::Action(Action::Rotate180) => vec![Key::Char('A')],
+            Control::Action(Action::SoftDrop) => vec![Key::Down],
+            Control::Action(Action::HardDrop) => vec![Key::Space],
+            Control::Action(Action::Hold) => vec![Key::Char('C'), Key::LShift],
+            Control::Action(Action::Pause) => vec![Key::Char('P'), Key::Escape],
+            Control::Restart => vec![Key::Char('R')],
+            Control::Quit => vec![Key::Char('Q')],
+        }
+    }
+}
+
+impl Input for Button {
+    const KIND: &'static str = "button";
+
+    fn defaults(control: Control) -> Vec<Self> {
+        match control {
+            Control::Action(Action::MoveLeft) => vec![Button::DPadLeft, Button::LeftStickLeft],
+            Control::Action(Action::MoveRight) => vec![Button::DPadRight, Button::LeftStickRight],
+            Control::Action(Action::RotateClockwise) => vec![Button::East],
+            Control::Action(Action::RotateCounterClockwise) => vec![Button::South],
+            Control::Action(Action::Rotate180) => vec![Button::North],
+            Control::Action(Action::SoftDrop) => vec![Button::DPadDown, Button::LeftStickDown],
+            Control::Action(Action::HardDrop) => vec![Button::DPadUp],
+            Control::Action(Action::Hold) => vec![Button::LeftTrigger, Button::RightTrigger],
+            Control::Action(Action::Pause) => vec![Button::Start],
+            Control::Restart => vec![Button::Select],
+            Control::Quit => vec![],
+        }
+    }
+}
+
+/// Inputs bound to every control; a control can have several inputs, an input belongs to at most one control.
+/// Controls missing from the configuration keep their default inputs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings<I>(BTreeMap<Control, Vec<I>>);
+
+pub type KeyBindings = Bindings<Key>;
+pub type ButtonBindings = Bindings<Button>;
+
+impl<I: Input> Default for Bindings<I> {
+    fn default() -> Self {
+        Self(Control::ALL.into_iter().map(|control| (control, I::defaults(control))).collect())
+    }
+}
+
+impl<I: Input> Bindings<I> {
+    pub fn inputs(&self, control: Control) -> &[I] {
+        self.0.get(&control).map_or(&[], |inputs| inputs.as_slice())
+    }
+
+    /// Control the input is bound to.
+    pub fn control(&self, input: I) -> Option<Control> {
+        Control::ALL.into_iter().find(|control| self.inputs(*control).contains(&input))
+    }
+
+    /// Adds the input to the control, unless it is already bound to another one, which is returned as the error.
+    pub fn bind(&mut self, control: Control, input: I) -> Result<(), Control> {
+        match self.control(input) {
+            Some(bound) if bound != control => Err(bound),
+            Some(_) => Ok(()),
+            None => {
+                self.0.entry(control).or_default().push(input);
+                Ok(())
+            },
+        }
+    }
+
+    /// Removes every input of the control.
+    pub fn clear(&mut self, control: Control) {
+        self.0.insert(control, Vec::new());
+    }
+
+    /// Finds inputs bound to more than one control.
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, control) in Control::ALL.iter().enumerate() {
+            for input in self.inputs(*control) {
+                if let Some(other) = Control::ALL[index + 1..].iter().find(|other| self.inputs(**other).contains(input)) {
+                    return Err(format!("{} '{}' is bound to both {} and {}", I::KIND, input, control.name(), other.name()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Written as a table of control names (see `Control::name`) and lists of input names.
+impl<I: Input> Serialize for Bindings<I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(control, inputs)| (control.name(), inputs)))
+    }
+}
+
+/// List of input names, a single name is accepted in place of a list.
+struct InputList<I>(Vec<I>);
+
+impl<'de, I: Input> Deserialize<'de> for InputList<I> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct InputListVisitor<I>(PhantomData<I>);
+
+        impl<'de, I: Input> serde::de::Visitor<'de> for InputListVisitor<I> {
+            type Value = InputList<I>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a {0} name or a list of {0} names", I::KIND)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<InputList<I>, E> {
+                name.parse().map(|input| InputList(vec![input])).map_err(E::custom)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<InputList<I>, A::Error> {
+                let mut inputs = Vec::new();
+                while let Some(input) = seq.next_element()? {
+                    inputs.push(input);
+                }
+                Ok(InputList(inputs))
+            }
+        }
+
+        deserializer.deserialize_any(InputListVisitor(PhantomData))
+    }
+}
+
+/// Inputs given in the configuration take precedence over the default inputs of the controls it does not mention.
+impl<'de, I: Input> Deserialize<'de> for Bindings<I> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut configured = BTreeMap::new();
+        for (name, InputList(inputs)) in BTreeMap::<String, InputList<I>>::deserialize(deserializer)? {
+            let control = Control::from_name(&name).ok_or_else(|| serde::de::Error::custom(format!("unknown control '{}', expected one of: {}",
+                name, Control::ALL.map(|control| control.name()).join(", "))))?;
+            configured.insert(control, inputs);
+        }
+        let bindings = Control::ALL.into_iter().map(|control| {
+            let inputs = configured.get(&control).cloned().unwrap_or_else(|| {
+                let mut inputs = I::defaults(control);
+                inputs.retain(|input| !configured.values().any(|configured: &Vec<I>| configured.contains(input)));
+                inputs
+            });
+            (control, inputs)
+        });
+        Ok(Bindings(bindings.collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const MOVE_LEFT: Control = Control::Action(Action::MoveLeft);
+    const HARD_DROP: Control = Control::Action(Action::HardDrop);
+
+    #[test]
+    fn defaults_are_valid() {
+        assert_eq!(KeyBindings::default().validate(), Ok(()));
+        assert_eq!(ButtonBindings::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn binding_an_input_of_another_control_is_rejected() {
+        let mut keys = KeyBindings::default();
+        assert_eq!(keys.bind(MOVE_LEFT, Key::Space), Err(HARD_DROP));
+        assert_eq!(keys.control(Key::Space), Some(HARD_DROP));
+        assert_eq!(keys.inputs(MOVE_LEFT), [Key::Left]);
+
+        let mut buttons = ButtonBindings::default();
+        assert_eq!(buttons.bind(Control::Quit, Button::Start), Err(Control::Action(Action::Pause)));
+        assert!(buttons.inputs(Control::Quit).is_empty());
+    }
+
+    #[test]
+    fn binding_a_free_or_own_input_adds_it_once() {
+        let mut keys = KeyBindings::default();
+        assert_eq!(keys.bind(MOVE_LEFT, Key::Char('J')), Ok(()));
+        assert_eq!(keys.bind(MOVE_LEFT, Key::Left), Ok(()));
+        assert_eq!(keys.inputs(MOVE_LEFT), [Key::Left, Key::Char('J')]);
+        assert_eq!(keys.control(Key::Char('J')), Some(MOVE_LEFT));
+
+        keys.clear(HARD_DROP);
+        assert_eq!(keys.bind(MOVE_LEFT, Key::Space), Ok(()));
+        assert_eq!(keys.validate(), Ok(()));
+    }
+
+    #[test]
+    fn configured_inputs_replace_the_defaults_of_other_controls() {
+        let config: Config = toml::from_str("[keys]\nmove_left = \"Space\"\nquit = [\"Q\", \"F10\"]").unwrap();
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.keys.inputs(MOVE_LEFT), [Key::Space]);
+        assert!(config.keys.inputs(HARD_DROP).is_empty());
+        assert_eq!(config.keys.inputs(Control::Quit), [Key::Char('Q'), Key::F(10)]);
+        assert_eq!(config.keys.inputs(Control::Restart), [Key::Char('R')]);
+    }
+
+    #[test]
+    fn duplicate_bindings_fail_validation() {
+        let config: Config = toml::from_str("[keys]\nmove_left = \"J\"\nhard_drop = [\"Space\", \"J\"]").unwrap();
+        assert_eq!(config.validate(), Err("keys: key 'J' is bound to both move_left and hard_drop".to_string()));
+
+        let config: Config = toml::from_str("[gamepad.buttons]\npause = \"South\"\nquit = \"South\"").unwrap();
+        assert_eq!(config.validate(), Err("gamepad.buttons: button 'South' is bound to both pause and quit".to_string()));
+    }
+
+    #[test]
+    fn unknown_controls_and_inputs_are_rejected() {
+        assert!(toml::from_str::<Config>("[keys]\njump = \"Space\"").is_err());
+        assert!(toml::from_str::<Config>("[keys]\nhold = \"Hyper\"").is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Pauses between a lock and the next tetromino, all measured in frames, and how they look.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Delays {
+    /// Number of frames the locked tetromino flashes before its full rows start clearing.
+    pub lock_flash: u32,
+    /// Line clear delay - number of frames full rows are animated before the rows above fall down.
+    pub line_clear: u32,
+    pub animation: ClearAnimation,
+    /// Appearance delay (ARE) - number of frames between the end of the line clear (or the lock) and the next spawn.
+    pub are: u32,
+    /// Initial rotation system - a rotation held when the tetromino spawns turns it right away; without it rotations
+    /// pressed during the delays are dropped.
+    pub irs: bool,
+    /// Initial hold system - hold held when the tetromino spawns swaps it right away; without it a hold pressed during
+    /// the delays is dropped.
+    pub ihs: bool,
+}
+
+impl Delays {
+    /// The next tetromino spawns in the frame the previous one locks.
+    pub const NONE: Delays = Delays {
+        lock_flash: 0,
+        line_clear: 0,
+        animation: ClearAnimation::Flash,
+        are: 0,
+        irs: false,
+        ihs: false,
+    };
+
+    /// Close to the NES: rows dissolve from the center outward, no initial actions.
+    pub const NES: Delays = Delays {
+        lock_flash: 0,
+        line_clear: 18,
+        animation: ClearAnimation::Dissolve,
+        are: 10,
+        irs: false,
+        ihs: false,
+    };
+
+    /// Close to the early levels of TGM, including its initial rotation and hold.
+    pub const TGM: Delays = Delays {
+        lock_flash: 3,
+        line_clear: 40,
+        animation: ClearAnimation::Fade,
+        are: 27,
+        irs: true,
+        ihs: true,
+    };
+}
+
+impl Default for Delays {
+    fn default() -> Self {
+        Delays::NONE
+    }
+}
+
+impl FromStr for Delays {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Delays::NONE),
+            "nes" => Ok(Delays::NES),
+            "tgm" => Ok(Delays::TGM),
+            _ => Err(format!("unknown delays '{}', expected one of: none, nes, tgm", s))
+        }
+    }
+}
+
+/// How full rows disappear during the line clear delay.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ClearAnimation {
+    /// The rows blink white.
+    Flash,
+    /// The rows fade out.
+    Fade,
+    /// The tiles vanish from the center of the row outward.
+    Dissolve,
+}
+
+/// Look of a tile of a clearing row.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClearingTile {
+    White,
+    /// In its own color with the given opacity, from 0 (invisible) to 1.
+    Faded(f32),
+}
+
+impl ClearAnimation {
+    /// Number of frames the rows stay white, and then in their colors, when flashing.
+    const FLASH_FRAMES: u32 = 4;
+
+    /// Look of the tile in column `x` of a row `width` tiles wide, `frame` frames into a line clear delay of `frames`.
+    pub fn tile(&self, x: usize, width: usize, frame: u32, frames: u32) -> ClearingTile {
+        let progress = frame as f32 / frames.max(1) as f32;
+        match self {
+            ClearAnimation::Flash if (frame / Self::FLASH_FRAMES).is_multiple_of(2) => ClearingTile::White,
+            ClearAnimation::Flash => ClearingTile::Faded(1f32),
+            ClearAnimation::Fade => ClearingTile::Faded(1f32 - progress),
+            ClearAnimation::Dissolve => {
+                // distance of the tile center from the row center, close to 0 in the middle and to 1 at the edges
+                let distance = (2f32 * x as f32 + 1f32 - width as f32).abs() / width.max(1) as f32;
+                ClearingTile::Faded(if distance < progress { 0f32 } else { 1f32 })
+            },
+        }
+    }
+}
@@ -1,125 +1,438 @@
-use crate::{board_tile::BoardTile, 
+use crate::{action::Action,
+    board_tile::BoardTile, 
     button_state::ButtonState, 
     gravity::Gravity, 
+    handling::AutoShift,
+    lock_delay::LockDelay,
     rotation::{Rotation, RotationDirection},
-    tetromino::{Tetromino, TetrominoKind}, 
-    wall_kicks};
-use ggez::graphics::{self, Color};
+    rotation_system::RotationSystem,
+    scoring::{LineClear, Score, TSpin},
+    settings::GameSettings,
+    statistics::Statistics,
+    tetromino::{Randomizer, Tetromino, TetrominoKind}};
 use rusttype::Point;
+use std::collections::VecDeque;
 
+/// Describes what happened when a tetromino locked, e.g. for on-screen callouts.
+#[derive(Clone, Copy, Debug)]
+pub struct LockEvent {
+    /// Frame (see `GameState::frame`) the tetromino locked at.
+    pub frame: u64,
+    pub kind: TetrominoKind,
+    pub clear: LineClear,
+    /// Whether the clear was a back-to-back difficult clear.
+    pub back_to_back: bool,
+    pub combo: Option<u32>,
+    pub points: u32,
+}
+
+/// Why a game topped out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameOverReason {
+    /// A new tetromino spawned overlapping the stack.
+    BlockOut,
+    /// A tetromino locked entirely above the visible field.
+    LockOut,
+    /// A tetromino locked partly above the visible field, only with `GameSettings::partial_lock_out`.
+    PartialLockOut,
+}
+
+/// What the game is doing, see `Delays` for the phases between two tetrominoes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+    /// A tetromino is falling and can be controlled.
+    Falling,
+    /// The tetromino that just locked flashes.
+    LockFlash,
+    /// Full rows are animated before they are removed.
+    LineClear,
+    /// Appearance delay (ARE) before the next tetromino spawns.
+    Entry,
+}
+
+impl GameOverReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameOverReason::BlockOut => "BLOCK OUT",
+            GameOverReason::LockOut => "LOCK OUT",
+            GameOverReason::PartialLockOut => "PARTIAL LOCK OUT",
+        }
+    }
+}
 
 pub struct GameState {
-    board: [BoardTile; crate::NUMBER_OF_TILES],
+    /// Tiles row by row, `settings.board_width` per row, the hidden buffer first and then the visible field.
+    board: Vec<BoardTile>,
     pub left_button_state: ButtonState,
     pub right_button_state: ButtonState,
     pub rotate_clockwise_button_state: ButtonState,
     pub rotate_counterclockwise_button_state: ButtonState,
+    pub rotate_180_button_state: ButtonState,
     pub hard_drop_button_state: ButtonState,
     pub soft_drop_button_state: ButtonState,
     pub hold_button_state: ButtonState,
+    pub pause_button_state: ButtonState,
+    seed: u64,
+    settings: GameSettings,
+    randomizer: Box<dyn Randomizer>,
+    rotation_system: Box<dyn RotationSystem>,
+    next_queue: VecDeque<TetrominoKind>,
+    held: Option<TetrominoKind>,
+    hold_available: bool,
     tetromino: Tetromino,
     ghost: Option<Tetromino>,
     vertical_gravity: f32,
-    horizontal_gravity: f32,
-    game_over: bool
+    horizontal_shift: i32,
+    auto_shift: AutoShift,
+    drop: Gravity,
+    lock_delay: LockDelay,
+    score: Score,
+    statistics: Statistics,
+    /// Direction and wall kick index of the last successful action, if that action was a rotation.
+    last_rotation: Option<(RotationDirection, usize)>,
+    last_lock: Option<LockEvent>,
+    /// Frame (see `frame`) every line was cleared at.
+    splits: Vec<u64>,
+    frame: u64,
+    paused: bool,
+    game_over: Option<GameOverReason>,
+    phase: Phase,
+    /// Number of frames spent in the current phase.
+    phase_frames: u32,
+    /// Full rows of the last lock, removed when the line clear delay ends.
+    clearing: Vec<usize>,
+    finished: bool,
+    timed_out: bool
 }
 
 impl GameState {
 
-    const HORIZONTAL_GRAVITY_FACTOR: f32 = 0.25f32;
-
-    pub fn new() -> Self {
-        let mut board = [BoardTile::empty(); crate::NUMBER_OF_TILES];
-        for i in 0..crate::NUMBER_OF_TILES {
-            let y = i / crate::BOARD_WIDTH;
-            let x = i - y * crate::BOARD_WIDTH;
-            board[i].x = x as u32;
-            board[i].y = y as u32;
-        }
-        let tetromino = Tetromino::new(TetrominoKind::T);
-        let ghost = Some(tetromino.to_ghost());
-        Self {
+    /// Creates a new game; the same seed fed with the same per-frame input always produces the same game.
+    pub fn new(seed: u64, settings: GameSettings) -> Self {
+        let board = (0..settings.buffer_height + settings.board_height)
+            .flat_map(|y| (0..settings.board_width).map(move |x| BoardTile { x: x as u32, y: y as u32, kind: None }))
+            .collect();
+        let mut randomizer = settings.randomizer.create(seed);
+        let preview_length = settings.preview_length.min(GameSettings::MAX_PREVIEW_LENGTH);
+        let mut next_queue: VecDeque<TetrominoKind> = (0..=preview_length).map(|_| randomizer.next()).collect();
+        let kind = next_queue.pop_front().unwrap();
+        let rotation_system = settings.rotation_system.create(settings.half_kick_table);
+        let tetromino = Tetromino::new(kind, rotation_system.as_ref());
+        let mut state = Self {
             board,
+            seed,
+            settings,
+            randomizer,
+            rotation_system,
+            next_queue,
+            held: None,
+            hold_available: true,
             tetromino,
-            ghost,
+            ghost: None,
             vertical_gravity: 0f32,
-            horizontal_gravity: 0f32,
+            horizontal_shift: 0,
+            auto_shift: AutoShift::new(),
+            drop: Gravity::Normal,
+            lock_delay: LockDelay::new(settings.lock_delay_mode, settings.move_reset_limit),
+            score: Score::new(settings.scoring),
+            statistics: Statistics::default(),
+            last_rotation: None,
+            last_lock: None,
+            splits: Vec::new(),
+            frame: 0,
             left_button_state: ButtonState::new(),
             right_button_state: ButtonState::new(),
             rotate_clockwise_button_state: ButtonState::new(),
             rotate_counterclockwise_button_state: ButtonState::new(),
+            rotate_180_button_state: ButtonState::new(),
             hard_drop_button_state: ButtonState::new(),
             soft_drop_button_state: ButtonState::new(),
             hold_button_state: ButtonState::new(),
-            game_over: false
-        }
+            pause_button_state: ButtonState::new(),
+            paused: false,
+            game_over: None,
+            phase: Phase::Falling,
+            phase_frames: 0,
+            clearing: Vec::new(),
+            finished: false,
+            timed_out: false
+        };
+        state.spawn_tetromino(kind);
+        state
     }
 
     fn move_tetromino_to_board(&mut self) {
         let shape = &self.tetromino.shape;
-        let mut y = self.tetromino.position.y;
-        for row in shape {
-            let mut x = self.tetromino.position.x;
-            for item in row {
+        for (y, row) in (self.tetromino.position.y..).zip(shape.iter()) {
+            for (x, item) in (self.tetromino.position.x..).zip(row.iter()) {
                 if *item {
-                    self.board[crate::BOARD_WIDTH * y as usize + x as usize].color = self.tetromino.color;
+                    self.board[self.settings.board_width * y as usize + x as usize].kind = Some(self.tetromino.kind);
                 }
-                x += 1;
             }
-            y += 1;
         }
     }
 
     fn new_tetromino(&mut self) {
-        self.tetromino = Tetromino::random();
-        self.ghost = Some(self.tetromino.to_ghost());
+        self.next_queue.push_back(self.randomizer.next());
+        let kind = self.next_queue.pop_front().unwrap();
+        self.spawn_tetromino(kind);
+        self.hold_available = true;
+    }
+
+    /// Spawns the tetromino at the guideline position: centered (rounded to the left), its top in row 22 counted from
+    /// the bottom of the field, then one row lower if nothing is in the way. Spawning over the stack is a block out.
+    fn spawn_tetromino(&mut self, kind: TetrominoKind) {
+        let mut tetromino = Tetromino::new(kind, self.rotation_system.as_ref());
+        let top = tetromino.shape.iter().position(|row| row.iter().any(|x| *x)).unwrap_or(0);
+        tetromino.position.x = (self.settings.board_width - tetromino.shape[0].len()) as i32 / 2;
+        tetromino.position.y = self.settings.buffer_height as i32 - 2 - top as i32;
+        if !self.can_move(&tetromino, Point {x: 0, y: 0}) {
+            self.game_over = Some(GameOverReason::BlockOut);
+        } else if self.can_move(&tetromino, Point {x: 0, y: 1}) {
+            tetromino.position.y += 1;
+        }
+        self.tetromino = tetromino;
+        self.update_ghost();
         self.vertical_gravity = 0f32;
+        self.lock_delay.start(self.tetromino.position.y);
+        self.last_rotation = None;
+        self.auto_shift.cut(self.settings.handling.dcd);
     }
 
-    fn remove_full_rows(board: &mut [BoardTile]) {
-        for row_number in 1..crate::BOARD_HEIGHT {
-            let start_index = row_number * crate::BOARD_WIDTH;
-            let end_index = start_index + crate::BOARD_WIDTH;
-            let is_full = board[start_index..end_index].iter().all(|x| x.color != Color::BLACK);
-            if is_full {
-                for i in (0..start_index).rev() {
-                    board[i + crate::BOARD_WIDTH].color = board[i].color;
-                }
-                for i in 0..crate::BOARD_WIDTH {
-                    board[i].color = Color::BLACK;
-                }
+    /// Indexes of the full rows, from the top.
+    fn full_rows(&self) -> Vec<usize> {
+        let width = self.settings.board_width;
+        (0..self.rows())
+            .filter(|row| self.board[row * width..(row + 1) * width].iter().all(|x| !x.is_empty()))
+            .collect()
+    }
+
+    /// Removes the rows cleared by the last lock, the rows above them fall down.
+    fn remove_cleared_rows(&mut self) {
+        let width = self.settings.board_width;
+        let board = &mut self.board;
+        for row_number in self.clearing.drain(..) {
+            let start_index = row_number * width;
+            for i in (0..start_index).rev() {
+                board[i + width].kind = board[i].kind;
             }
+            for tile in board.iter_mut().take(width) {
+                tile.kind = None;
+            }
+        }
+    }
+
+    /// Number of rows of the board, including the hidden buffer.
+    fn rows(&self) -> usize {
+        self.settings.buffer_height + self.settings.board_height
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn settings(&self) -> &GameSettings {
+        &self.settings
+    }
+
+    pub fn button(&mut self, action: Action) -> &mut ButtonState {
+        match action {
+            Action::MoveLeft => &mut self.left_button_state,
+            Action::MoveRight => &mut self.right_button_state,
+            Action::RotateClockwise => &mut self.rotate_clockwise_button_state,
+            Action::RotateCounterClockwise => &mut self.rotate_counterclockwise_button_state,
+            Action::Rotate180 => &mut self.rotate_180_button_state,
+            Action::SoftDrop => &mut self.soft_drop_button_state,
+            Action::HardDrop => &mut self.hard_drop_button_state,
+            Action::Hold => &mut self.hold_button_state,
+            Action::Pause => &mut self.pause_button_state,
+        }
+    }
+
+    /// Tiles of the board row by row, from the top left corner of the hidden buffer.
+    pub fn board(&self) -> &[BoardTile] {
+        &self.board
+    }
+
+    /// Board width in tiles.
+    pub fn width(&self) -> usize {
+        self.settings.board_width
+    }
+
+    /// Height of the visible field in tiles.
+    pub fn height(&self) -> usize {
+        self.settings.board_height
+    }
+
+    /// Number of hidden rows above the visible field.
+    pub fn buffer_height(&self) -> usize {
+        self.settings.buffer_height
+    }
+
+    /// The tile moved from board coordinates to the coordinates of a view of the visible field with `buffer_rows` rows
+    /// of the hidden buffer above it, `None` for tiles above the view.
+    pub fn to_view(&self, tile: &BoardTile, buffer_rows: usize) -> Option<BoardTile> {
+        let top = self.buffer_height().saturating_sub(buffer_rows) as u32;
+        (tile.y >= top).then(|| BoardTile { y: tile.y - top, ..*tile })
+    }
+
+    /// The falling tetromino, none during the delays between two tetrominoes.
+    pub fn tetromino(&self) -> Option<&Tetromino> {
+        (self.phase == Phase::Falling).then_some(&self.tetromino)
+    }
+
+    /// The tetromino that just locked, while it flashes.
+    pub fn lock_flash(&self) -> Option<&Tetromino> {
+        (self.phase == Phase::LockFlash).then_some(&self.tetromino)
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Number of frames spent in the current phase.
+    pub fn phase_frames(&self) -> u32 {
+        self.phase_frames
+    }
+
+    /// Indexes of the rows animated during the line clear delay, in board coordinates.
+    pub fn clearing_rows(&self) -> &[usize] {
+        if self.phase == Phase::LineClear { &self.clearing } else { &[] }
+    }
+
+    /// Shape of the kind in the state it spawns in, for the hold and next previews.
+    pub fn preview(&self, kind: TetrominoKind) -> Vec<Vec<bool>> {
+        Tetromino::new(kind, self.rotation_system.as_ref()).shape
+    }
+
+    /// Upcoming tetromino kinds, the first one spawns next.
+    pub fn next_queue(&self) -> &VecDeque<TetrominoKind> {
+        &self.next_queue
+    }
+
+    pub fn ghost(&self) -> Option<&Tetromino> {
+        self.ghost.as_ref()
+    }
+
+    pub fn level(&self) -> u32 {
+        self.settings.start_level.max(1) + self.score.lines() / self.settings.lines_per_level.max(1)
+    }
+
+    pub fn score(&self) -> &Score {
+        &self.score
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// Event describing the most recently locked tetromino.
+    pub fn last_lock(&self) -> Option<LockEvent> {
+        self.last_lock
+    }
+
+    /// Number of played (not paused) frames since the first tetromino appeared, it stops when the game ends
+    /// and serves as the game timer.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Timer value (see `frame`) at the end of the frame every line was cleared in, indexed by line number minus one.
+    pub fn splits(&self) -> &[u64] {
+        &self.splits
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over.is_some()
+    }
+
+    pub fn game_over_reason(&self) -> Option<GameOverReason> {
+        self.game_over
+    }
+
+    /// Whether the goal of the game mode has been reached.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Whether the time limit of the game mode has run out; unlike game over the game ended without topping out.
+    pub fn is_timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Whether the game ended in any way, after that `update_game` does nothing.
+    pub fn has_ended(&self) -> bool {
+        self.game_over.is_some() || self.finished || self.timed_out
+    }
+
+    /// Tetromino kind kept in the hold slot.
+    pub fn held(&self) -> Option<TetrominoKind> {
+        self.held
+    }
+
+    /// Hold can be used once per tetromino, it becomes available again after the tetromino locks.
+    pub fn can_hold(&self) -> bool {
+        self.hold_available
+    }
 
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn handle_pause(&mut self) {
+        if self.pause_button_state.should_handle_once() {
+            self.paused = !self.paused;
+            self.pause_button_state.handled_once();
         }
     }
 
-    pub fn hold(&self) -> bool {
-        self.hold_button_state.is_pressed()
+    fn handle_hold(&mut self) {
+        if !self.hold_button_state.should_handle_once() {
+            return;
+        }
+        self.hold_button_state.handled_once();
+        self.hold();
+    }
+
+    /// Swaps the tetromino with the held one, or the next one when nothing is held, once per tetromino.
+    fn hold(&mut self) {
+        if !self.hold_available {
+            return;
+        }
+        match self.held.replace(self.tetromino.kind) {
+            Some(kind) => self.spawn_tetromino(kind),
+            None => self.new_tetromino(),
+        }
+        self.hold_available = false;
     }
 
     fn handle_vertical(&mut self) {
+        let normal = self.settings.gravity_curve.gravity(self.level());
         if self.hard_drop_button_state.should_handle_once() {
-            self.vertical_gravity = Gravity::HardDrop.value();
+            self.drop = Gravity::HardDrop;
+            self.vertical_gravity = self.drop.value(normal, self.settings.handling.sdf);
             self.hard_drop_button_state.handled_once();
         } else if self.soft_drop_button_state.is_pressed() {
-            self.vertical_gravity += Gravity::SoftDrop.value();
+            self.drop = Gravity::SoftDrop;
+            self.vertical_gravity += self.drop.value(normal, self.settings.handling.sdf);
         } else {
-            self.vertical_gravity += Gravity::Normal.value();
+            self.drop = Gravity::Normal;
+            self.vertical_gravity += self.drop.value(normal, self.settings.handling.sdf);
         }
     }
 
     fn handle_horizontal(&mut self) {
-        if self.left_button_state.should_handle_once() {
-            self.horizontal_gravity = -1f32;
-            self.left_button_state.handled_once();
-        } else if self.left_button_state.is_long_pressed() {
-            self.horizontal_gravity -= GameState::HORIZONTAL_GRAVITY_FACTOR;
-        }
-        if self.right_button_state.should_handle_once() {
-            self.horizontal_gravity = 1f32;
-            self.right_button_state.handled_once();
-        } else if self.right_button_state.is_long_pressed() {
-            self.horizontal_gravity += GameState::HORIZONTAL_GRAVITY_FACTOR;
+        let direction = self.shift_direction();
+        self.horizontal_shift = self.auto_shift.update(direction, &self.settings.handling);
+    }
+
+    /// When both Left and Right are held, the one pressed last wins.
+    fn shift_direction(&self) -> i32 {
+        match (self.left_button_state.pressed_frames(), self.right_button_state.pressed_frames()) {
+            (Some(left), Some(right)) => if left < right { -1 } else { 1 },
+            (Some(_), None) => -1,
+            (None, Some(_)) => 1,
+            (None, None) => 0,
         }
     }
 
@@ -135,42 +448,66 @@ impl GameState {
             self.try_rotate(RotationDirection::CounterClockwise);
             self.rotate_counterclockwise_button_state.handled_once();
         }
+        if self.rotate_180_button_state.should_handle_once() {
+            self.try_rotate(RotationDirection::Half);
+            self.rotate_180_button_state.handled_once();
+        }
     }
 
-    fn try_rotate(&mut self, direction: RotationDirection) {
+    /// Rotates the tetromino using the first wall kick that fits, returns whether it rotated.
+    fn try_rotate(&mut self, direction: RotationDirection) -> bool {
+        let wall_kicks = self.rotation_system.kicks(&self.tetromino, direction, &|point| self.is_occupied(point));
         let mut clone = self.tetromino.clone();
-        let wall_kicks = Self::get_wall_kick_vectors(clone.kind, clone.current_rotation, direction);
-        clone.rotate(direction);
-        for kick in wall_kicks {
-            if Self::can_move(&clone, &self.board, kick) {
+        clone.rotate(direction, self.rotation_system.as_ref());
+        for (index, kick) in wall_kicks.into_iter().enumerate() {
+            if self.can_move(&clone, kick) {
                 clone.position.x += kick.x;
                 clone.position.y += kick.y;
                 self.tetromino = clone;
-                break;
+                self.lock_delay.moved();
+                self.last_rotation = Some((direction, index));
+                self.auto_shift.cut(self.settings.handling.dcd);
+                return true;
             }   
         }
+        false
     }
-    
-    fn get_wall_kick_vectors(tetromino: TetrominoKind, start: Rotation, direction: RotationDirection) -> [Point<i32>; 5] {
-        let table = match tetromino {
-            TetrominoKind::O => panic!("'O' tetromino does not support rotation and does not have any wall kick vector"),
-            TetrominoKind::I => wall_kicks::I_WALL_KICKS,
-            _ => wall_kicks::WALL_KICKS
-        };
 
-        let index: usize;
-        if direction == RotationDirection::Clockwise {
-            index = 2 * start as usize
-        } else {
-            let finish  = start.next(RotationDirection::CounterClockwise);
-            index = 2 * finish as usize + 1
+    /// Advances the game by exactly one frame.
+    pub fn update_game(&mut self) {
+        if self.has_ended() {
+            return;
         }
+        self.handle_pause();
+        if !self.paused {
+            self.update_frame();
+            self.frame += 1;
+            if !self.has_ended() && self.settings.mode.time_limit().is_some_and(|limit| self.frame >= limit) {
+                self.timed_out = true;
+            }
+        }
+        self.tick_buttons();
+    }
 
-        table[index]
+    fn tick_buttons(&mut self) {
+        self.left_button_state.tick();
+        self.right_button_state.tick();
+        self.rotate_clockwise_button_state.tick();
+        self.rotate_counterclockwise_button_state.tick();
+        self.rotate_180_button_state.tick();
+        self.hard_drop_button_state.tick();
+        self.soft_drop_button_state.tick();
+        self.hold_button_state.tick();
+        self.pause_button_state.tick();
     }
 
-    pub fn update_game(&mut self) {
-        if self.game_over {
+    fn update_frame(&mut self) {
+        if self.phase != Phase::Falling {
+            self.update_delay();
+            return;
+        }
+        self.handle_hold();
+        if self.game_over.is_some() {
             return;
         }
         self.handle_rotation();
@@ -178,135 +515,272 @@ impl GameState {
         self.handle_horizontal();
         let round_finish = self.move_tetromino();
         if round_finish {
-            self.move_tetromino_to_board();
-            Self::remove_full_rows(&mut self.board);
-        }
-
-        if self.board[0..crate::BOARD_WIDTH].iter().any(|x| x.color != Color::BLACK) {
-            self.game_over = true;
+            if let Some(reason) = self.lock_out() {
+                self.game_over = Some(reason);
+                return;
+            }
+            self.lock_tetromino();
+            if self.settings.mode.is_complete(self.score.lines()) {
+                self.remove_cleared_rows();
+                self.finished = true;
+                return;
+            }
+            self.end_phase();
             return;
         }
-        
-        if round_finish {
-            self.new_tetromino();
-        }
+        self.update_ghost();
+    }
 
+    fn update_ghost(&mut self) {
         let mut ghost = self.tetromino.to_ghost();
-        while Self::can_move(&ghost, &self.board, Point {x: 0, y: 1}) {
+        while self.can_move(&ghost, Point {x: 0, y: 1}) {
             ghost.position.y += 1;
         }
         self.ghost = Some(ghost)
     }
 
-    pub fn draw_game(&self, canvas: &mut graphics::Canvas) {
-        if self.game_over {
-            Self::draw_text("GAME", 0.45f32, canvas);
-            Self::draw_text("OVER", 0.55f32, canvas);
-            return;
+    /// Advances a frame of the delays between two tetrominoes. Auto-shift keeps charging, presses of rotations and
+    /// hold are kept for IRS and IHS or dropped.
+    fn update_delay(&mut self) {
+        let delays = self.settings.delays;
+        let mut dropped = Vec::new();
+        if !delays.irs {
+            dropped.extend([
+                &mut self.rotate_clockwise_button_state,
+                &mut self.rotate_counterclockwise_button_state,
+                &mut self.rotate_180_button_state,
+            ]);
         }
+        if !delays.ihs {
+            dropped.push(&mut self.hold_button_state);
+        }
+        for button in dropped.into_iter().filter(|button| button.should_handle_once()) {
+            button.handled_once();
+        }
+        self.auto_shift.update(self.shift_direction(), &self.settings.handling);
+        self.phase_frames += 1;
+        if self.phase_frames >= self.phase_length(self.phase) {
+            self.end_phase();
+        }
+    }
 
-        for seg in self.board {
-            canvas.draw(
-                &graphics::Quad,
-                graphics::DrawParam::new()
-                    .dest_rect(seg.into())
-                    .color(seg.color)
-            );
-        }
-
-        if self.ghost.is_some() {
-            let tiles = self.ghost.as_ref().unwrap().tiles();
-            for tile in tiles{
-                canvas.draw(
-                    &graphics::Quad,
-                    graphics::DrawParam::new()
-                        .dest_rect(tile.into())
-                        .color(tile.color),
-                );
+    /// Number of frames the phase lasts with the current settings and the last lock.
+    fn phase_length(&self, phase: Phase) -> u32 {
+        let delays = self.settings.delays;
+        match phase {
+            Phase::Falling => 0,
+            Phase::LockFlash => delays.lock_flash,
+            Phase::LineClear if self.clearing.is_empty() => 0,
+            Phase::LineClear => delays.line_clear,
+            Phase::Entry => delays.are,
+        }
+    }
+
+    /// Moves on from the current phase to the next one that lasts at least a frame, spawning the next tetromino
+    /// when the entry delay is over.
+    fn end_phase(&mut self) {
+        let mut phase = self.phase;
+        loop {
+            phase = match phase {
+                Phase::Falling => Phase::LockFlash,
+                Phase::LockFlash => Phase::LineClear,
+                Phase::LineClear => {
+                    self.remove_cleared_rows();
+                    Phase::Entry
+                },
+                Phase::Entry => {
+                    self.phase = Phase::Falling;
+                    self.spawn_next();
+                    return;
+                },
+            };
+            if self.phase_length(phase) > 0 {
+                self.phase = phase;
+                self.phase_frames = 0;
+                self.ghost = None;
+                return;
             }
         }
+    }
+
+    /// Spawns the next tetromino, then applies IHS and IRS with the buttons held at that moment.
+    fn spawn_next(&mut self) {
+        self.new_tetromino();
+        let delays = self.settings.delays;
+        if delays.ihs && self.game_over.is_none() && self.hold_button_state.is_pressed() {
+            self.hold_button_state.handled_once();
+            self.hold();
+        }
+        if !delays.irs || self.game_over.is_some() || self.tetromino.kind == TetrominoKind::O {
+            return;
+        }
+        let buttons = [
+            (RotationDirection::Clockwise, &mut self.rotate_clockwise_button_state),
+            (RotationDirection::CounterClockwise, &mut self.rotate_counterclockwise_button_state),
+            (RotationDirection::Half, &mut self.rotate_180_button_state),
+        ];
+        let direction = buttons.into_iter().find(|(_, button)| button.is_pressed()).map(|(direction, button)| {
+            button.handled_once();
+            direction
+        });
+        if let Some(direction) = direction {
+            self.try_rotate(direction);
+            self.update_ghost();
+        }
+    }
 
-        let tetromino_tiles = self.tetromino.tiles();
-        for tile in tetromino_tiles{
-            canvas.draw(
-                &graphics::Quad,
-                graphics::DrawParam::new()
-                    .dest_rect(tile.into())
-                    .color(tile.color),
-            );
+    /// Checks whether locking the tetromino where it is tops out.
+    fn lock_out(&self) -> Option<GameOverReason> {
+        let tiles = self.tetromino.tiles();
+        let hidden = tiles.iter().filter(|tile| (tile.y as usize) < self.settings.buffer_height).count();
+        if hidden == tiles.len() {
+            Some(GameOverReason::LockOut)
+        } else if hidden > 0 && self.settings.partial_lock_out {
+            Some(GameOverReason::PartialLockOut)
+        } else {
+            None
         }
     }
 
-    fn draw_text(text: &str, height_percentage: f32, canvas: &mut graphics::Canvas) {
-        let mut text = graphics::Text::new(text);
-        text.set_layout(graphics::TextLayout::center());
-        text.set_scale(32f32);
-        let width = (crate::BOARD_WIDTH * crate::TILE_SIZE) as f32 / 2f32;
-        let height = (crate::BOARD_HEIGHT * crate::TILE_SIZE) as f32 * height_percentage;
-        let game_draw_param = graphics::DrawParam::from([width, height]).color(Color::WHITE);
-        canvas.draw(&text, game_draw_param);
+    fn lock_tetromino(&mut self) {
+        let t_spin = self.detect_t_spin();
+        self.move_tetromino_to_board();
+        self.clearing = self.full_rows();
+        let lines = self.clearing.len() as u32;
+        self.splits.extend((0..lines).map(|_| self.frame + 1));
+        let width = self.settings.board_width;
+        let clear = LineClear {
+            lines,
+            t_spin,
+            perfect_clear: lines > 0 && self.board.chunks(width).enumerate()
+                .all(|(row, tiles)| self.clearing.contains(&row) || tiles.iter().all(|x| x.is_empty())),
+        };
+        let level = self.level();
+        let back_to_back = clear.is_difficult() && self.score.is_back_to_back();
+        let points = self.score.line_clear(clear, level);
+        self.statistics.record(clear);
+        self.last_lock = Some(LockEvent {
+            frame: self.frame,
+            kind: self.tetromino.kind,
+            clear,
+            back_to_back,
+            combo: self.score.combo(),
+            points,
+        });
+    }
+
+    /// Classifies the lock of a T tetromino using the 3-corner rule: when the last successful action was
+    /// a rotation and at least 3 corners of its 3x3 box are occupied, it is a T-spin if both corners in front
+    /// of the flat side are occupied (or a 90° rotation used the last wall kick), otherwise a mini T-spin.
+    fn detect_t_spin(&self) -> TSpin {
+        let (direction, kick) = match self.last_rotation {
+            Some(rotation) if self.tetromino.kind == TetrominoKind::T => rotation,
+            _ => return TSpin::None,
+        };
+        let (center_x, center_y) = self.rotation_system.t_center(self.tetromino.current_rotation);
+        let occupied = |x: i32, y: i32| {
+            self.is_occupied(Point {x: self.tetromino.position.x + center_x as i32 + x, y: self.tetromino.position.y + center_y as i32 + y})
+        };
+        let top_left = occupied(-1, -1);
+        let top_right = occupied(1, -1);
+        let bottom_left = occupied(-1, 1);
+        let bottom_right = occupied(1, 1);
+        let corners = [top_left, top_right, bottom_left, bottom_right].iter().filter(|&&x| x).count();
+        if corners < 3 {
+            return TSpin::None;
+        }
+        let front = match self.tetromino.current_rotation {
+            Rotation::_0 => top_left && top_right,
+            Rotation::R => top_right && bottom_right,
+            Rotation::_2 => bottom_left && bottom_right,
+            Rotation::L => top_left && bottom_left,
+        };
+        if front || direction != RotationDirection::Half && kick == 4 {
+            TSpin::Full
+        } else {
+            TSpin::Mini
+        }
     }
 
+    /// Moves the tetromino and returns whether it should be locked.
     fn move_tetromino(&mut self) -> bool {
         self.move_horizontally();
-        self.move_vertically()
+        self.move_vertically();
+        self.should_lock()
+    }
+
+    fn should_lock(&mut self) -> bool {
+        if self.can_move(&self.tetromino, Point {x: 0, y: 1}) {
+            return false;
+        }
+        if let Gravity::HardDrop = self.drop {
+            return true;
+        }
+        let delay = self.settings.gravity_curve.lock_delay(self.level());
+        self.lock_delay.grounded(delay)
     }
 
     fn move_horizontally(&mut self) {
-        if self.horizontal_gravity >= 1f32 {
-            while self.horizontal_gravity >= 1f32 {
-                if !Self::can_move(&self.tetromino, &self.board, Point {x: 1, y: 0})  {
-                    self.horizontal_gravity = 0f32;
-                    return;
-                }
-                self.tetromino.position.x += 1;
-                self.horizontal_gravity -= 1f32;
-            }
-            self.horizontal_gravity = 0f32;
-        } else if self.horizontal_gravity <= -1f32 {
-            while self.horizontal_gravity <= -1f32 {
-                if !Self::can_move(&self.tetromino, &self.board, Point {x: -1, y: 0})  {
-                    self.horizontal_gravity = 0f32;
-                    return;
-                }
-                self.tetromino.position.x -= 1;
-                self.horizontal_gravity += 1f32;
+        let step = self.horizontal_shift.signum();
+        let start = self.tetromino.position.x;
+        while self.horizontal_shift != 0 {
+            if !self.can_move(&self.tetromino, Point {x: step, y: 0}) {
+                break;
             }
-            self.horizontal_gravity = 0f32;
+            self.tetromino.position.x += step;
+            self.horizontal_shift -= step;
+        }
+        self.horizontal_shift = 0;
+        // an instant shift to the wall is a single move
+        if self.tetromino.position.x != start {
+            self.lock_delay.moved();
+            self.last_rotation = None;
         }
     }
 
-    fn move_vertically(&mut self) -> bool {
+    fn move_vertically(&mut self) {
         if self.vertical_gravity >= 1f32 {
             //move tetromino down
             while self.vertical_gravity >= 1f32 {
-                if !Self::can_move(&self.tetromino, &self.board, Point {x: 0, y: 1}) {
+                if !self.can_move(&self.tetromino, Point {x: 0, y: 1}) {
                     self.vertical_gravity = 0f32;
-                    return true;
+                    return;
                 }
                 self.tetromino.position.y += 1;
                 self.vertical_gravity -= 1f32;
+                self.lock_delay.stepped_down(self.tetromino.position.y);
+                self.last_rotation = None;
+                match self.drop {
+                    Gravity::SoftDrop => self.score.soft_drop(1),
+                    Gravity::HardDrop => self.score.hard_drop(1),
+                    Gravity::Normal => {}
+                }
             }
             self.vertical_gravity = 0f32; // reset gravity to avoid errors related to the cumulation of fractional parts.
         }
-        false
     }
 
-    fn can_move(tetromino: &Tetromino, board: &[BoardTile], offset_vector: Point<i32> ) -> bool {
-        let mut y = tetromino.position.y as i32 +tetromino.shape.len() as i32 + offset_vector.y - 1;
+    /// Whether the board tile is filled or outside the board.
+    fn is_occupied(&self, point: Point<i32>) -> bool {
+        point.x < 0 || point.x >= self.settings.board_width as i32 || point.y < 0 || point.y >= self.rows() as i32
+            || !self.board[point.y as usize * self.settings.board_width + point.x as usize].is_empty()
+    }
+
+    fn can_move(&self, tetromino: &Tetromino, offset_vector: Point<i32> ) -> bool {
+        let width = self.settings.board_width as i32;
+        let height = self.rows() as i32;
+        let mut y = tetromino.position.y + tetromino.shape.len() as i32 + offset_vector.y - 1;
         
         for row in tetromino.shape.iter().rev() {
-            let mut x = tetromino.position.x as i32 + offset_vector.x;
-            for tile in row {
+            for (x, tile) in (tetromino.position.x + offset_vector.x..).zip(row.iter()) {
                 if *tile {
-                    if x < 0 || x >= crate::BOARD_WIDTH.try_into().unwrap() || y < 0 || y >= crate::BOARD_HEIGHT.try_into().unwrap() {
+                    if x < 0 || x >= width || y < 0 || y >= height {
                         return false;
                     }
-                    if board[(y as usize) * crate::BOARD_WIDTH + (x as usize)].color != Color::BLACK {
+                    if !self.board[(y * width + x) as usize].is_empty() {
                         return false;
                     }
                 }
-                x += 1;
             }
             y -= 1;
         }
@@ -315,4 +789,646 @@ impl GameState {
     }
 
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Range;
+    use crate::{delays::Delays, handling::Handling, mode::GameMode, personal_best::{PersonalBests, SprintRecord}, rotation_system::RotationSystemKind};
+
+    /// Tiles of the rows at the bottom of the board drawn as text: `#` is a filled tile, any letter a tile of the
+    /// tetromino, `.` an empty tile.
+    fn tiles(state: &GameState, rows: &[&str], piece: bool) -> Vec<(u32, u32)> {
+        let top = state.rows() - rows.len();
+        let mut tiles = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c != '.' && (c == '#') != piece {
+                    tiles.push((x as u32, (top + y) as u32));
+                }
+            }
+        }
+        tiles.sort();
+        tiles
+    }
+
+    fn piece_tiles(tetromino: &Tetromino) -> Vec<(u32, u32)> {
+        let mut tiles: Vec<(u32, u32)> = tetromino.tiles().iter().map(|tile| (tile.x, tile.y)).collect();
+        tiles.sort();
+        tiles
+    }
+
+    /// A game whose board ends with `rows`, with a tetromino of `kind` in the rotation state and position drawn there.
+    fn setup(system: RotationSystemKind, kind: TetrominoKind, rows: &[&str]) -> GameState {
+        setup_with(GameSettings { rotation_system: system, ..GameSettings::default() }, kind, rows)
+    }
+
+    fn setup_with(settings: GameSettings, kind: TetrominoKind, rows: &[&str]) -> GameState {
+        let mut state = GameState::new(0, settings);
+        let width = state.width();
+        for (x, y) in tiles(&state, rows, false) {
+            state.board[y as usize * width + x as usize].kind = Some(TetrominoKind::J);
+        }
+        let expected = tiles(&state, rows, true);
+        let mut tetromino = Tetromino::new(kind, state.rotation_system.as_ref());
+        for _ in 0..4 {
+            let (x, y) = (expected[0].0 as i32, expected.iter().map(|tile| tile.1).min().unwrap() as i32);
+            tetromino.position = Point {x: 0, y: 0};
+            let at_origin = piece_tiles(&tetromino);
+            let (origin_x, origin_y) = (at_origin[0].0 as i32, at_origin.iter().map(|tile| tile.1).min().unwrap() as i32);
+            tetromino.position = Point {x: x - origin_x, y: y - origin_y};
+            if piece_tiles(&tetromino) == expected {
+                state.tetromino = tetromino;
+                return state;
+            }
+            tetromino.rotate(RotationDirection::Clockwise, state.rotation_system.as_ref());
+        }
+        panic!("the drawn tiles are not a {:?} tetromino", kind);
+    }
+
+    /// Rotates the tetromino, checks where it ended up, then locks it and returns the clear.
+    fn rotate_and_lock(state: &mut GameState, direction: RotationDirection, rotation: Rotation, rows: &[&str]) -> LineClear {
+        assert!(state.try_rotate(direction), "rotation failed");
+        assert_eq!(state.tetromino.current_rotation, rotation);
+        assert_eq!(piece_tiles(&state.tetromino), tiles(state, rows, true));
+        state.lock_tetromino();
+        state.last_lock.unwrap().clear
+    }
+
+    #[test]
+    fn t_spin_triple_uses_the_last_kick() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::T, &[
+            ".T#.......",
+            "TTT#######",
+            "##.#######",
+            "#..#######",
+            "##.#######",
+        ]);
+        let clear = rotate_and_lock(&mut state, RotationDirection::CounterClockwise, Rotation::L, &[
+            "..#.......",
+            "...#######",
+            "##T#######",
+            "#TT#######",
+            "##T#######",
+        ]);
+        assert_eq!((clear.lines, clear.t_spin), (3, TSpin::Full));
+    }
+
+    #[test]
+    fn stsd_kicks_two_rows_down() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::T, &[
+            ".......#T.",
+            "#######TTT",
+            "#######.##",
+            "#######..#",
+            "#######.#.",
+        ]);
+        let clear = rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::R, &[
+            ".......#..",
+            "#######...",
+            "#######T##",
+            "#######TT#",
+            "#######T#.",
+        ]);
+        assert_eq!((clear.lines, clear.t_spin), (2, TSpin::Full));
+    }
+
+    #[test]
+    fn fin_t_spin_double_is_upgraded_by_the_last_kick() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::T, &[
+            "....##....",
+            "...TTT....",
+            "####T.####",
+            "####..####",
+            "#####.####",
+        ]);
+        let clear = rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::L, &[
+            "....##....",
+            "..........",
+            "####.T####",
+            "####TT####",
+            "#####T####",
+        ]);
+        // only 3 corners are occupied and one of the front ones is free, the fifth kick makes it a full T-spin
+        assert_eq!((clear.lines, clear.t_spin), (2, TSpin::Full));
+    }
+
+    #[test]
+    fn neo_t_spin_double_kicks_straight_down() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::T, &[
+            "....#.....",
+            "...TTT....",
+            "####T#####",
+            "####..####",
+            "####.####.",
+        ]);
+        let clear = rotate_and_lock(&mut state, RotationDirection::CounterClockwise, Rotation::R, &[
+            "....#.....",
+            "..........",
+            "####T#####",
+            "####TT####",
+            "####T####.",
+        ]);
+        assert_eq!((clear.lines, clear.t_spin), (2, TSpin::Full));
+    }
+
+    #[test]
+    fn iso_t_spin_double_kicks_straight_down() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::T, &[
+            "....#.....",
+            "...TTT....",
+            "####T#####",
+            "###..#####",
+            "####.####.",
+        ]);
+        let clear = rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::L, &[
+            "....#.....",
+            "..........",
+            "####T#####",
+            "###TT#####",
+            "####T####.",
+        ]);
+        assert_eq!((clear.lines, clear.t_spin), (2, TSpin::Full));
+    }
+
+    #[test]
+    fn i_kicks_off_the_right_wall() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::I, &[
+            ".........I",
+            ".........I",
+            ".........I",
+            ".........I",
+        ]);
+        let clear = rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::_2, &[
+            "..........",
+            "..........",
+            "......IIII",
+            "..........",
+        ]);
+        assert_eq!(clear.lines, 0);
+    }
+
+    #[test]
+    fn i_kicks_up_off_the_floor() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::I, &[
+            "..........",
+            "..........",
+            "..........",
+            "...IIII...",
+        ]);
+        rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::R, &[
+            "......I...",
+            "......I...",
+            "......I...",
+            "......I...",
+        ]);
+    }
+
+    #[test]
+    fn srs_plus_i_kicks_right_first() {
+        let rows = [
+            "..........",
+            "...IIII...",
+            "..........",
+            ".....#....",
+        ];
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::I, &rows);
+        rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::R, &[
+            "...I......",
+            "...I......",
+            "...I......",
+            "...I.#....",
+        ]);
+        let mut state = setup(RotationSystemKind::SrsPlus, TetrominoKind::I, &rows);
+        rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::R, &[
+            "......I...",
+            "......I...",
+            "......I...",
+            ".....#I...",
+        ]);
+    }
+
+    #[test]
+    fn ars_does_not_kick_when_blocked_in_the_center_column() {
+        let mut state = setup(RotationSystemKind::Ars, TetrominoKind::T, &[
+            "....#.....",
+            "...TTT....",
+            "....T.....",
+        ]);
+        assert!(!state.try_rotate(RotationDirection::Clockwise));
+        assert!(!state.try_rotate(RotationDirection::CounterClockwise));
+        let mut state = setup(RotationSystemKind::Ars, TetrominoKind::L, &[
+            "...#......",
+            "...LLL....",
+            "...L......",
+        ]);
+        rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::L, &[
+            "...#LL....",
+            ".....L....",
+            ".....L....",
+        ]);
+    }
+
+    #[test]
+    fn ars_t_spin_corners_follow_the_t_in_state_0() {
+        // the flat side of the ARS T rests on the bottom of its box in state 0, one row lower than in SRS
+        let mut state = setup(RotationSystemKind::Ars, TetrominoKind::T, &[
+            "....T.....",
+            "...#TT....",
+            "###.T.####",
+            "#########.",
+        ]);
+        let clear = rotate_and_lock(&mut state, RotationDirection::CounterClockwise, Rotation::_0, &[
+            "..........",
+            "...#T.....",
+            "###TTT####",
+            "#########.",
+        ]);
+        assert_eq!((clear.lines, clear.t_spin), (1, TSpin::Mini));
+    }
+
+    #[test]
+    fn nrs_never_kicks() {
+        let mut state = setup(RotationSystemKind::Nrs, TetrominoKind::I, &[
+            ".........I",
+            ".........I",
+            ".........I",
+            ".........I",
+        ]);
+        assert!(!state.try_rotate(RotationDirection::Clockwise));
+        let mut state = setup(RotationSystemKind::Nrs, TetrominoKind::T, &[
+            "..........",
+            "TTT.......",
+            ".T........",
+        ]);
+        rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::L, &[
+            ".T........",
+            "TT........",
+            ".T........",
+        ]);
+    }
+
+    #[test]
+    fn delays_hold_back_the_line_clear_and_the_next_spawn() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::I, &[
+            "...IIII...",
+            "###....###",
+        ]);
+        state.settings.delays = Delays { lock_flash: 2, line_clear: 5, are: 3, ..Delays::NONE };
+        let bottom = state.rows() - 1;
+        let bottom_row = |state: &GameState| state.board[bottom * state.width()..].iter().filter(|tile| !tile.is_empty()).count();
+        state.hard_drop_button_state.key_down();
+        state.update_game();
+        assert_eq!(state.phase(), Phase::LockFlash);
+        assert!(state.tetromino().is_none() && state.lock_flash().is_some());
+        for _ in 0..2 {
+            state.update_game();
+        }
+        assert_eq!(state.phase(), Phase::LineClear);
+        assert_eq!(state.clearing_rows(), &[bottom]);
+        assert_eq!(bottom_row(&state), 10);
+        for _ in 0..5 {
+            state.update_game();
+        }
+        assert_eq!(state.phase(), Phase::Entry);
+        assert_eq!(bottom_row(&state), 0);
+        for _ in 0..3 {
+            state.update_game();
+        }
+        assert_eq!(state.phase(), Phase::Falling);
+        assert!(state.tetromino().is_some());
+    }
+
+    #[test]
+    fn no_delays_spawn_in_the_lock_frame() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::I, &[
+            "...IIII...",
+            "###....###",
+        ]);
+        state.hard_drop_button_state.key_down();
+        state.update_game();
+        assert_eq!(state.phase(), Phase::Falling);
+        assert_eq!(state.last_lock().unwrap().clear.lines, 1);
+        assert!(state.board().iter().all(|tile| tile.is_empty()));
+    }
+
+    /// Locks the I drawn at the bottom and presses rotate clockwise and hold during the entry delay.
+    fn press_during_entry(delays: Delays) -> GameState {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::I, &["...IIII..."]);
+        state.settings.delays = Delays { are: 5, ..delays };
+        state.next_queue[0] = TetrominoKind::T;
+        state.next_queue[1] = TetrominoKind::S;
+        state.hard_drop_button_state.key_down();
+        state.update_game();
+        state.rotate_clockwise_button_state.key_down();
+        state.hold_button_state.key_down();
+        for _ in 0..5 {
+            state.update_game();
+        }
+        state
+    }
+
+    #[test]
+    fn initial_hold_and_rotation_act_on_the_spawning_tetromino() {
+        let state = press_during_entry(Delays { irs: true, ihs: true, ..Delays::NONE });
+        assert_eq!(state.held(), Some(TetrominoKind::T));
+        let tetromino = state.tetromino().unwrap();
+        assert_eq!((tetromino.kind, tetromino.current_rotation), (TetrominoKind::S, Rotation::R));
+    }
+
+    #[test]
+    fn presses_during_delays_are_dropped_without_initial_actions() {
+        let mut state = press_during_entry(Delays::NONE);
+        state.update_game();
+        assert_eq!(state.held(), None);
+        let tetromino = state.tetromino().unwrap();
+        assert_eq!((tetromino.kind, tetromino.current_rotation), (TetrominoKind::T, Rotation::_0));
+    }
+
+    /// Presses the button of `action` for a single frame.
+    fn tap(state: &mut GameState, action: Action) {
+        state.button(action).key_down();
+        state.update_game();
+        state.button(action).key_up();
+    }
+
+    #[test]
+    fn the_next_tetromino_is_the_head_of_the_preview() {
+        let mut spawned = Vec::new();
+        for preview_length in [0, 3, GameSettings::MAX_PREVIEW_LENGTH] {
+            let mut state = GameState::new(0, GameSettings { preview_length, ..GameSettings::default() });
+            let mut kinds = vec![state.tetromino().unwrap().kind];
+            assert_eq!(state.next_queue().len(), preview_length);
+            for _ in 0..7 {
+                let head = state.next_queue().front().copied();
+                tap(&mut state, Action::HardDrop);
+                let kind = state.tetromino().unwrap().kind;
+                assert!(head.is_none_or(|head| head == kind));
+                assert_eq!(state.next_queue().len(), preview_length);
+                kinds.push(kind);
+            }
+            spawned.push(kinds);
+        }
+        // the preview only shows the sequence, its length does not change it
+        assert!(spawned.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
+    #[test]
+    fn hold_is_used_once_per_tetromino() {
+        let mut state = GameState::new(0, GameSettings::default());
+        state.spawn_tetromino(TetrominoKind::T);
+        let next = state.next_queue()[0];
+        tap(&mut state, Action::Hold);
+        assert_eq!(state.held(), Some(TetrominoKind::T));
+        assert_eq!(state.tetromino().unwrap().kind, next);
+        assert!(!state.can_hold());
+        tap(&mut state, Action::Hold);
+        assert_eq!(state.held(), Some(TetrominoKind::T));
+        assert_eq!(state.tetromino().unwrap().kind, next);
+        tap(&mut state, Action::HardDrop);
+        assert!(state.can_hold());
+        let kind = state.tetromino().unwrap().kind;
+        tap(&mut state, Action::Hold);
+        assert_eq!(state.held(), Some(kind));
+        assert_eq!(state.tetromino().unwrap().kind, TetrominoKind::T);
+    }
+
+    #[test]
+    fn held_tetromino_comes_back_at_its_spawn_state() {
+        let mut state = GameState::new(0, GameSettings::default());
+        state.spawn_tetromino(TetrominoKind::T);
+        let spawn = state.tetromino().unwrap().clone();
+        tap(&mut state, Action::RotateClockwise);
+        tap(&mut state, Action::MoveLeft);
+        let moved = state.tetromino().unwrap();
+        assert_ne!((moved.current_rotation, moved.position.x), (spawn.current_rotation, spawn.position.x));
+        tap(&mut state, Action::Hold);
+        tap(&mut state, Action::HardDrop);
+        tap(&mut state, Action::Hold);
+        let tetromino = state.tetromino().unwrap();
+        assert_eq!(tetromino.kind, TetrominoKind::T);
+        assert_eq!(tetromino.current_rotation, spawn.current_rotation);
+        assert_eq!((tetromino.position.x, tetromino.position.y), (spawn.position.x, spawn.position.y));
+    }
+
+    /// Plays `frames` frames, then spawns an I over a bottom row filled but under it and hard drops it, clearing
+    /// a line in the next frame.
+    fn clear_line(state: &mut GameState, frames: u32) {
+        for _ in 0..frames {
+            state.update_game();
+        }
+        state.spawn_tetromino(TetrominoKind::I);
+        let width = state.width();
+        let bottom = state.rows() - 1;
+        for x in (0..3).chain(7..width) {
+            state.board[bottom * width + x].kind = Some(TetrominoKind::J);
+        }
+        tap(state, Action::HardDrop);
+    }
+
+    fn sprint(lines: u32) -> GameState {
+        GameState::new(0, GameSettings { mode: GameMode::Sprint { lines }, ..GameSettings::default() })
+    }
+
+    #[test]
+    fn sprint_finishes_at_the_line_target() {
+        for lines in GameMode::SPRINT_LINES {
+            let mut state = sprint(lines);
+            // the timer starts with the first tetromino
+            assert!(state.tetromino().is_some());
+            assert_eq!(state.frame(), 0);
+            for _ in 0..lines {
+                assert!(!state.has_ended());
+                clear_line(&mut state, 9);
+            }
+            assert!(state.is_finished() && !state.is_game_over());
+            assert_eq!(state.score().lines(), lines);
+            assert_eq!(state.frame(), lines as u64 * 10);
+            assert_eq!(state.splits(), (1..=lines as u64).map(|line| line * 10).collect::<Vec<_>>());
+            state.update_game();
+            assert_eq!(state.frame(), lines as u64 * 10);
+        }
+    }
+
+    #[test]
+    fn splits_are_compared_with_the_personal_best() {
+        let mut state = sprint(20);
+        for _ in 0..20 {
+            clear_line(&mut state, 9);
+        }
+        let mut bests = PersonalBests::default();
+        assert!(bests.submit(&state));
+        assert_eq!(bests.sprint(20), Some(&SprintRecord { frames: 200, splits: state.splits().to_vec() }));
+        let record = bests.sprint(20).unwrap().clone();
+
+        let mut state = sprint(20);
+        assert_eq!(record.delta(state.splits()), None);
+        clear_line(&mut state, 4);
+        assert_eq!(record.delta(state.splits()), Some(-5));
+        clear_line(&mut state, 19);
+        assert_eq!(record.delta(state.splits()), Some(5));
+        for _ in 2..20 {
+            clear_line(&mut state, 10);
+        }
+        assert!(state.is_finished());
+        assert_eq!(record.delta(state.splits()), Some(23));
+        // a slower game is not a new personal best
+        assert!(!bests.submit(&state));
+        assert_eq!(bests.sprint(20), Some(&record));
+    }
+
+    #[test]
+    fn ultra_times_out_after_two_minutes() {
+        // tall enough for the tetrominoes falling without input not to top out in two minutes
+        let settings = GameSettings { mode: GameMode::Ultra, board_height: GameSettings::MAX_BOARD_HEIGHT, ..GameSettings::default() };
+        let mut state = GameState::new(0, settings);
+        for _ in 1..120 * crate::FRAMES_PER_SECOND {
+            state.update_game();
+        }
+        assert!(!state.has_ended());
+        state.update_game();
+        assert!(state.is_timed_out() && !state.is_game_over() && !state.is_finished());
+        assert_eq!(state.frame(), GameMode::ULTRA_FRAMES);
+        // the game is over, input does nothing
+        let (pieces, score) = (state.statistics().pieces(), state.score().points());
+        let position = state.tetromino().unwrap().position;
+        tap(&mut state, Action::MoveLeft);
+        tap(&mut state, Action::HardDrop);
+        assert_eq!(state.frame(), GameMode::ULTRA_FRAMES);
+        assert_eq!((state.statistics().pieces(), state.score().points()), (pieces, score));
+        assert_eq!(state.tetromino().unwrap().position, position);
+    }
+
+    /// Fills `columns` of the board `rows`, counted from the top of the hidden buffer.
+    fn fill(state: &mut GameState, rows: Range<usize>, columns: Range<usize>) {
+        let width = state.width();
+        for y in rows {
+            for x in columns.clone() {
+                state.board[y * width + x].kind = Some(TetrominoKind::J);
+            }
+        }
+    }
+
+    #[test]
+    fn spawning_over_the_stack_is_a_block_out() {
+        let mut state = setup(RotationSystemKind::Srs, TetrominoKind::O, &[
+            "....OO....",
+            "....OO....",
+        ]);
+        let buffer = state.buffer_height();
+        fill(&mut state, buffer - 2..buffer, 3..7);
+        tap(&mut state, Action::HardDrop);
+        assert_eq!(state.statistics().pieces(), 1);
+        assert_eq!(state.game_over_reason(), Some(GameOverReason::BlockOut));
+        assert!(state.has_ended());
+    }
+
+    /// Locks a T at the left wall over a stack filling the board from `row` down, but its last column, away from
+    /// where the next tetromino spawns.
+    fn lock_over_stack(partial_lock_out: bool, row: usize) -> GameState {
+        let mut state = GameState::new(0, GameSettings { partial_lock_out, ..GameSettings::default() });
+        let (rows, width) = (state.rows(), state.width());
+        fill(&mut state, row..rows, 0..width - 1);
+        state.spawn_tetromino(TetrominoKind::T);
+        assert!(!state.is_game_over());
+        state.tetromino.position.x = 0;
+        tap(&mut state, Action::HardDrop);
+        state
+    }
+
+    #[test]
+    fn locking_above_the_visible_field_is_a_lock_out() {
+        for partial_lock_out in [false, true] {
+            let state = lock_over_stack(partial_lock_out, GameSettings::default().buffer_height);
+            assert_eq!(state.game_over_reason(), Some(GameOverReason::LockOut));
+        }
+    }
+
+    #[test]
+    fn locking_partly_above_the_visible_field_is_a_partial_lock_out_when_enabled() {
+        // the T rests on the second visible row, its top tile stays hidden
+        let row = GameSettings::default().buffer_height + 1;
+        let state = lock_over_stack(false, row);
+        assert!(!state.is_game_over());
+        assert_eq!(state.statistics().pieces(), 1);
+        assert!(state.tetromino().is_some());
+        let state = lock_over_stack(true, row);
+        assert_eq!(state.game_over_reason(), Some(GameOverReason::PartialLockOut));
+    }
+
+    #[test]
+    fn stack_in_the_hidden_buffer_does_not_end_the_game() {
+        let mut state = GameState::new(0, GameSettings::default());
+        let rows = state.rows();
+        fill(&mut state, 0..rows, 0..1);
+        tap(&mut state, Action::HardDrop);
+        assert!(!state.is_game_over());
+        assert_eq!(state.statistics().pieces(), 1);
+        assert!(state.tetromino().is_some());
+    }
+
+    /// Rows of a board with the shortest buffer and field: `top` over a stack filling the rows but their last tile.
+    fn small_rows<'a>(top: &[&'a str]) -> Vec<&'a str> {
+        top.iter().copied().chain(std::iter::repeat("#########."))
+            .take(GameSettings::MIN_BUFFER_HEIGHT + GameSettings::MIN_BOARD_HEIGHT)
+            .collect()
+    }
+
+    /// A game on the board of `small_rows`, with a tetromino of `kind` drawn in `top`.
+    fn small_board(kind: TetrominoKind, top: &[&str]) -> GameState {
+        let settings = GameSettings {
+            buffer_height: GameSettings::MIN_BUFFER_HEIGHT,
+            board_height: GameSettings::MIN_BOARD_HEIGHT,
+            ..GameSettings::default()
+        };
+        setup_with(settings, kind, &small_rows(top))
+    }
+
+    #[test]
+    fn full_rows_of_the_hidden_buffer_are_cleared() {
+        let mut state = small_board(TetrominoKind::I, &[
+            "I#########",
+            "I.........",
+            "I.........",
+            "I.........",
+        ]);
+        tap(&mut state, Action::HardDrop);
+        assert!(!state.is_game_over());
+        assert_eq!(state.last_lock().unwrap().clear.lines, 1);
+        let width = state.width();
+        assert!(state.board()[..width].iter().all(|tile| tile.is_empty()));
+    }
+
+    #[test]
+    fn i_kicks_up_into_the_hidden_buffer() {
+        let mut state = small_board(TetrominoKind::I, &[
+            "..........",
+            "..........",
+            "..........",
+            "...IIII...",
+        ]);
+        rotate_and_lock(&mut state, RotationDirection::Clockwise, Rotation::R, &small_rows(&[
+            "......I...",
+            "......I...",
+            "......I...",
+            "......I...",
+        ]));
+    }
+
+    #[test]
+    fn instant_shift_uses_one_lock_delay_reset() {
+        let handling = Handling { das: 1, arr: 0, ..Handling::default() };
+        let mut state = setup_with(GameSettings { handling, move_reset_limit: 3, ..GameSettings::default() }, TetrominoKind::T, &[
+            ".....T....",
+            "....TTT...",
+        ]);
+        state.button(Action::MoveLeft).key_down();
+        // a tap of one cell, then the rest of the way to the wall, two of the three resets
+        state.update_game();
+        state.update_game();
+        let tetromino = state.tetromino().unwrap();
+        assert_eq!(piece_tiles(tetromino).iter().map(|tile| tile.0).min(), Some(0));
+        assert_eq!(state.statistics().pieces(), 0);
+    }
+}
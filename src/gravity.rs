@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
 #[derive(Clone, Copy)]
 pub enum Gravity {
     Normal,
@@ -6,11 +9,139 @@ pub enum Gravity {
 }
 
 impl Gravity {
-    pub fn value(&self) -> f32 {
+    /// Gravity value, `normal` is the gravity of the current level and `soft_drop_factor` its soft drop multiplier.
+    pub fn value(&self, normal: f32, soft_drop_factor: f32) -> f32 {
+        match self {
+            Gravity::Normal => normal,
+            Gravity::SoftDrop => normal * soft_drop_factor.max(1f32),
+            Gravity::HardDrop => f32::INFINITY,
+        }
+    }
+}
+
+/// Maps levels (starting from 1) to the gravity and the lock delay of that level.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GravityCurve {
+    /// Guideline formula: one row every (0.8 - (level - 1) * 0.007)^(level - 1) seconds, up to 20G. The formula
+    /// reaches 20G by level 20, higher levels keep that gravity.
+    Guideline,
+    /// NES NTSC frames-per-row table, level 1 is NES level 0. Pieces lock as soon as they touch down.
+    Nes,
+    /// TGM internal gravity table (in 1/256 G), one level here counts as 30 TGM levels.
+    Tgm,
+}
+
+impl GravityCurve {
+    pub const MAX_GRAVITY: f32 = 20f32;
+
+    /// Last level the guideline formula is evaluated for, its base goes negative from level 116 on.
+    const GUIDELINE_LAST_LEVEL: u32 = 20;
+
+    const NES_FRAMES_PER_ROW: [u32; 30] = [
+        48, 43, 38, 33, 28, 23, 18, 13, 8, 6,
+        5, 5, 5, 4, 4, 4, 3, 3, 3, 2,
+        2, 2, 2, 2, 2, 2, 2, 2, 2, 1,
+    ];
+
+    /// Pairs of (TGM level, internal gravity) - the gravity applies from that level on.
+    const TGM_INTERNAL_GRAVITY: [(u32, u32); 30] = [
+        (0, 4), (30, 6), (35, 8), (40, 10), (50, 12), (60, 16), (70, 32), (80, 48), (90, 64), (100, 80),
+        (120, 96), (140, 112), (160, 128), (170, 144), (200, 4), (220, 32), (230, 64), (233, 96), (236, 128), (239, 160),
+        (243, 192), (247, 224), (251, 256), (300, 512), (330, 768), (360, 1024), (400, 1280), (420, 1024), (450, 768), (500, 5120),
+    ];
+
+    const TGM_LEVELS_PER_LEVEL: u32 = 30;
+
+    /// Gravity (in G, rows per frame) of the given level.
+    pub fn gravity(&self, level: u32) -> f32 {
+        let level = level.max(1);
+        let gravity = match self {
+            GravityCurve::Guideline => {
+                let level = level.min(Self::GUIDELINE_LAST_LEVEL);
+                let seconds_per_row = (0.8f32 - (level - 1) as f32 * 0.007f32).powi(level as i32 - 1);
+                1f32 / (seconds_per_row * crate::FRAMES_PER_SECOND as f32)
+            },
+            GravityCurve::Nes => {
+                let index = ((level - 1) as usize).min(Self::NES_FRAMES_PER_ROW.len() - 1);
+                1f32 / Self::NES_FRAMES_PER_ROW[index] as f32
+            },
+            GravityCurve::Tgm => {
+                let tgm_level = (level - 1).saturating_mul(Self::TGM_LEVELS_PER_LEVEL);
+                let internal = Self::TGM_INTERNAL_GRAVITY.iter()
+                    .rev()
+                    .find(|(from, _)| *from <= tgm_level)
+                    .map(|(_, gravity)| *gravity)
+                    .unwrap();
+                internal as f32 / 256f32
+            },
+        };
+        gravity.clamp(0f32, Self::MAX_GRAVITY)
+    }
+
+    /// Number of frames a tetromino can stay on the ground before it locks.
+    pub fn lock_delay(&self, level: u32) -> u32 {
         match self {
-            Gravity::Normal => 1f32 / 64f32,
-            Gravity::SoftDrop => 0.7f32,
-            Gravity::HardDrop => 20f32,
+            GravityCurve::Guideline => 30,
+            GravityCurve::Nes => 0,
+            GravityCurve::Tgm => {
+                let tgm_level = (level.max(1) - 1).saturating_mul(Self::TGM_LEVELS_PER_LEVEL);
+                match tgm_level {
+                    0..=499 => 30,
+                    500..=599 => 25,
+                    600..=699 => 20,
+                    700..=799 => 17,
+                    _ => 15,
+                }
+            },
+        }
+    }
+}
+
+impl FromStr for GravityCurve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "guideline" => Ok(GravityCurve::Guideline),
+            "nes" => Ok(GravityCurve::Nes),
+            "tgm" => Ok(GravityCurve::Tgm),
+            _ => Err(format!("unknown gravity curve '{}', expected one of: guideline, nes, tgm", s))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [GravityCurve; 3] = [GravityCurve::Guideline, GravityCurve::Nes, GravityCurve::Tgm];
+
+    #[test]
+    fn gravity_is_finite_and_bounded_at_any_level() {
+        let levels = (0..=1000).chain([10_000, 1_000_000, u32::MAX / 2, u32::MAX - 1, u32::MAX]);
+        for level in levels {
+            for curve in CURVES {
+                let gravity = curve.gravity(level);
+                assert!(gravity.is_finite(), "{:?} level {}: {}", curve, level, gravity);
+                assert!((0f32..=GravityCurve::MAX_GRAVITY).contains(&gravity), "{:?} level {}: {}", curve, level, gravity);
+                curve.lock_delay(level);
+            }
+        }
+    }
+
+    #[test]
+    fn gravity_never_decreases_with_guideline_and_nes() {
+        for curve in [GravityCurve::Guideline, GravityCurve::Nes] {
+            for level in 1..200 {
+                assert!(curve.gravity(level + 1) >= curve.gravity(level), "{:?} level {}", curve, level);
+            }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn guideline_reaches_max_gravity() {
+        assert_eq!(GravityCurve::Guideline.gravity(1), 1f32 / 60f32);
+        assert_eq!(GravityCurve::Guideline.gravity(GravityCurve::GUIDELINE_LAST_LEVEL), GravityCurve::MAX_GRAVITY);
+        assert_eq!(GravityCurve::Guideline.gravity(116), GravityCurve::MAX_GRAVITY);
+    }
+}
@@ -0,0 +1,412 @@
+use ggez::graphics::{self, Color};
+use tetris_rs::{board_tile::BoardTile, delays::ClearingTile, game_state::LockEvent, high_scores::HighScore, mode::{self, GameMode}, personal_best::SprintRecord, replay::Playback, tetromino::TetrominoKind, GameState};
+
+const GHOST_COLOR: Color = Color::new(100f32 / 255f32, 100f32 / 255f32, 100f32 / 255f32, 1f32);
+const PANEL_COLOR: Color = Color::new(30f32 / 255f32, 30f32 / 255f32, 30f32 / 255f32, 1f32);
+const AHEAD_COLOR: Color = Color::new(80f32 / 255f32, 220f32 / 255f32, 80f32 / 255f32, 1f32);
+const BEHIND_COLOR: Color = Color::new(240f32 / 255f32, 80f32 / 255f32, 80f32 / 255f32, 1f32);
+const PREVIEW_TILE_SIZE: f32 = crate::TILE_SIZE as f32 * 0.75f32;
+/// Number of frames a line clear callout stays on the screen.
+const CALLOUT_FRAMES: u64 = 90;
+/// Rows of the hidden buffer shown above the board, where only tetrominoes are drawn so that spawning ones peek out.
+const BUFFER_ROWS: usize = 2;
+
+/// Sizes of the screen, measured in tiles of `crate::TILE_SIZE` logical pixels; the canvas is scaled to the window.
+#[derive(Clone, Copy)]
+pub struct Layout {
+    pub board_width: usize,
+    pub board_height: usize,
+    /// Width of each of the side panels, in tiles.
+    pub side_panel_width: usize,
+}
+
+impl Layout {
+    pub fn screen_size(&self) -> (f32, f32) {
+        (
+            ((self.board_width + 2 * self.side_panel_width) * crate::TILE_SIZE) as f32,
+            ((BUFFER_ROWS + self.board_height) * crate::TILE_SIZE) as f32,
+        )
+    }
+
+    /// The board is drawn between the hold panel (left) and the next queue panel (right).
+    fn board_left(&self) -> f32 {
+        (self.side_panel_width * crate::TILE_SIZE) as f32
+    }
+}
+
+/// Draws the game, `personal_best` is the record a Sprint is compared against.
+pub fn draw_game(layout: &Layout, state: &GameState, personal_best: Option<&SprintRecord>, canvas: &mut graphics::Canvas) {
+    if let Some(reason) = state.game_over_reason() {
+        draw_text(layout, "GAME", 0.45f32, canvas);
+        draw_text(layout, "OVER", 0.55f32, canvas);
+        draw_scaled_text(layout, reason.name(), 20f32, 0.65f32, canvas);
+        return;
+    }
+    if state.is_finished() {
+        draw_text(layout, "FINISHED", 0.35f32, canvas);
+        draw_text(layout, &mode::format_time(state.frame()), 0.45f32, canvas);
+        match personal_best {
+            Some(record) if record.frames <= state.frame() => draw_text(layout, &format!("PB {}", mode::format_time(record.frames)), 0.6f32, canvas),
+            _ => draw_text(layout, "NEW BEST!", 0.6f32, canvas),
+        }
+        return;
+    }
+    if state.is_timed_out() {
+        draw_text(layout, "TIME UP", 0.1f32, canvas);
+        draw_results(layout, state, canvas);
+        return;
+    }
+
+    for tile in state.board() {
+        let seg = match state.to_view(tile, BUFFER_ROWS) {
+            Some(seg) if seg.y as usize >= BUFFER_ROWS || !seg.is_empty() => seg,
+            _ => continue,
+        };
+        let color = if state.clearing_rows().contains(&(tile.y as usize)) { clearing_color(state, &seg) } else { tile_color(&seg) };
+        draw_tile(layout, &seg, color, canvas);
+    }
+
+    if let Some(ghost) = state.ghost() {
+        for tile in ghost.tiles().iter().filter_map(|tile| state.to_view(tile, BUFFER_ROWS)) {
+            draw_tile(layout, &tile, GHOST_COLOR, canvas);
+        }
+    }
+
+    if let Some(tetromino) = state.tetromino() {
+        for tile in tetromino.tiles().iter().filter_map(|tile| state.to_view(tile, BUFFER_ROWS)) {
+            draw_tile(layout, &tile, tile_color(&tile), canvas);
+        }
+    }
+    if let Some(tetromino) = state.lock_flash() {
+        for tile in tetromino.tiles().iter().filter_map(|tile| state.to_view(tile, BUFFER_ROWS)) {
+            draw_tile(layout, &tile, Color::WHITE, canvas);
+        }
+    }
+
+    draw_hold(layout, state, canvas);
+    draw_stats(layout, state, personal_best, canvas);
+    draw_callout(layout, state, canvas);
+    draw_next_queue(layout, state, canvas);
+
+    if state.is_paused() {
+        draw_text(layout, "PAUSED", 0.5f32, canvas);
+    }
+}
+
+/// Draws the replayed game with the playback position and speed below the next queue.
+pub fn draw_playback(layout: &Layout, playback: &Playback, canvas: &mut graphics::Canvas) {
+    draw_game(layout, playback.state(), None, canvas);
+
+    let center_x = layout.board_left() * 1.5f32 + (layout.board_width * crate::TILE_SIZE) as f32;
+    let status = if playback.is_paused() { "PAUSED".to_string() } else { format!("x{}", playback.speed()) };
+    let lines = [
+        "REPLAY".to_string(),
+        format!("{} / {}", format_frames(playback.frame()), format_frames(playback.length())),
+        status,
+    ];
+    let mut top = 17f32 * crate::TILE_SIZE as f32;
+    for line in lines {
+        let mut text = graphics::Text::new(line);
+        text.set_layout(graphics::TextLayout::center());
+        text.set_scale(16f32);
+        canvas.draw(&text, graphics::DrawParam::from([center_x, top]).color(Color::WHITE));
+        top += crate::TILE_SIZE as f32;
+    }
+}
+
+/// Formats a number of frames as minutes and seconds.
+fn format_frames(frames: u64) -> String {
+    let seconds = frames / tetris_rs::FRAMES_PER_SECOND;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Asks for the name to put on the leaderboard.
+pub fn draw_name_entry(layout: &Layout, name: &str, rank: usize, canvas: &mut graphics::Canvas) {
+    draw_text(layout, "HIGH SCORE", 0.25f32, canvas);
+    draw_text(layout, &format!("#{}", rank + 1), 0.35f32, canvas);
+    draw_text(layout, "ENTER YOUR NAME", 0.5f32, canvas);
+    draw_text(layout, &format!("{}_", name), 0.6f32, canvas);
+}
+
+/// Lists the high scores of the mode, highlighting the entry at `highlight`.
+pub fn draw_leaderboard(layout: &Layout, mode: GameMode, entries: &[HighScore], highlight: Option<usize>, canvas: &mut graphics::Canvas) {
+    // left edges of the rank and of the `HighScore::columns` values on a screen of the default width, 22 tiles
+    const COLUMNS_LEFT: [f32; 7] = [10f32, 35f32, 145f32, 215f32, 265f32, 295f32, 360f32];
+    let columns_scale = layout.screen_size().0 / (22 * crate::TILE_SIZE) as f32;
+    let mut title = graphics::Text::new(format!("{} - HIGH SCORES", mode.name().to_uppercase()));
+    title.set_layout(graphics::TextLayout::center());
+    title.set_scale(24f32);
+    canvas.draw(&title, graphics::DrawParam::from([layout.screen_size().0 / 2f32, crate::TILE_SIZE as f32]).color(Color::WHITE));
+
+    let header = std::iter::once("#".to_string()).chain(HighScore::COLUMN_TITLES.iter().map(|title| title.to_string()));
+    let rows = entries.iter().enumerate()
+        .map(|(rank, entry)| (Some(rank), std::iter::once(format!("{}.", rank + 1)).chain(entry.columns()).collect::<Vec<String>>()));
+    let mut top = 3f32 * crate::TILE_SIZE as f32;
+    for (rank, row) in std::iter::once((None, header.collect::<Vec<String>>())).chain(rows) {
+        let color = if rank.is_some() && rank == highlight { kind_color(TetrominoKind::O) } else { Color::WHITE };
+        for (value, left) in row.into_iter().zip(COLUMNS_LEFT) {
+            let mut text = graphics::Text::new(value);
+            text.set_scale(14f32);
+            canvas.draw(&text, graphics::DrawParam::from([left * columns_scale, top]).color(color));
+        }
+        top += crate::TILE_SIZE as f32 * 1.25f32;
+    }
+    if entries.is_empty() {
+        draw_text(layout, "NO ENTRIES", 0.5f32, canvas);
+    }
+
+}
+
+/// Draws the title and the items of a menu, highlighting the selected one.
+pub fn draw_menu(layout: &Layout, title: &str, items: &[String], selected: usize, canvas: &mut graphics::Canvas) {
+    let center_x = layout.screen_size().0 / 2f32;
+    let mut text = graphics::Text::new(title);
+    text.set_layout(graphics::TextLayout::center());
+    text.set_scale(32f32);
+    canvas.draw(&text, graphics::DrawParam::from([center_x, 3f32 * crate::TILE_SIZE as f32]).color(Color::WHITE));
+
+    // long menus are packed closer together to fit above the hint
+    let mut top = 6f32 * crate::TILE_SIZE as f32;
+    let spacing = ((layout.screen_size().1 - top - 2f32 * crate::TILE_SIZE as f32) / items.len() as f32).min(2f32 * crate::TILE_SIZE as f32);
+    for (index, item) in items.iter().enumerate() {
+        let mut text = graphics::Text::new(item.as_str());
+        text.set_layout(graphics::TextLayout::center());
+        text.set_scale(spacing.min(20f32));
+        let color = if index == selected { kind_color(TetrominoKind::O) } else { Color::WHITE };
+        canvas.draw(&text, graphics::DrawParam::from([center_x, top]).color(color));
+        top += spacing;
+    }
+}
+
+/// Darkens everything drawn so far, e.g. the game behind the pause menu.
+pub fn draw_shade(layout: &Layout, canvas: &mut graphics::Canvas) {
+    canvas.draw(
+        &graphics::Quad,
+        graphics::DrawParam::new()
+            .dest_rect(graphics::Rect::new(0f32, 0f32, layout.screen_size().0, layout.screen_size().1))
+            .color(Color::new(0f32, 0f32, 0f32, 0.75f32))
+    );
+}
+
+/// Draws a line about the available keys at the bottom of the screen.
+pub fn draw_hint(layout: &Layout, hint: &str, canvas: &mut graphics::Canvas) {
+    let mut hint = graphics::Text::new(hint);
+    hint.set_layout(graphics::TextLayout::center());
+    hint.set_scale(14f32);
+    canvas.draw(&hint, graphics::DrawParam::from([layout.screen_size().0 / 2f32, layout.screen_size().1 - crate::TILE_SIZE as f32]).color(Color::WHITE));
+}
+
+/// Lists the score, line and piece counts and the breakdown of clears below the title.
+fn draw_results(layout: &Layout, state: &GameState, canvas: &mut graphics::Canvas) {
+    let statistics = state.statistics();
+    let mut lines = vec![
+        format!("SCORE {}", state.score().points()),
+        format!("LINES {}", state.score().lines()),
+        format!("PIECES {}", statistics.pieces()),
+        format!("PPS {:.2}", statistics.pieces_per_second(state.frame())),
+        String::new(),
+    ];
+    lines.extend(statistics.breakdown().into_iter().map(|(name, count)| format!("{} x{}", name, count)));
+    let center_x = layout.board_left() + (layout.board_width * crate::TILE_SIZE) as f32 / 2f32;
+    let mut top = 4f32 * crate::TILE_SIZE as f32;
+    for line in lines {
+        let mut text = graphics::Text::new(line);
+        text.set_layout(graphics::TextLayout::center());
+        text.set_scale(20f32);
+        canvas.draw(&text, graphics::DrawParam::from([center_x, top]).color(Color::WHITE));
+        top += crate::TILE_SIZE as f32 * 1.25f32;
+    }
+}
+
+fn draw_hold(layout: &Layout, state: &GameState, canvas: &mut graphics::Canvas) {
+    let center_x = draw_panel(layout, "HOLD", 0f32, canvas);
+    if let Some(kind) = state.held() {
+        let color = if state.can_hold() { kind_color(kind) } else { GHOST_COLOR };
+        draw_preview(&state.preview(kind), color, center_x, 2f32 * crate::TILE_SIZE as f32, canvas);
+    }
+}
+
+fn draw_stats(layout: &Layout, state: &GameState, personal_best: Option<&SprintRecord>, canvas: &mut graphics::Canvas) {
+    let center_x = layout.board_left() / 2f32;
+    let score = state.score();
+    let stats = match state.settings().mode {
+        GameMode::Endless => vec![
+            ("SCORE", score.points().to_string(), Color::WHITE),
+            ("LEVEL", state.level().to_string(), Color::WHITE),
+            ("LINES", score.lines().to_string(), Color::WHITE),
+        ],
+        GameMode::Ultra => vec![
+            ("TIME", mode::format_time(GameMode::ULTRA_FRAMES.saturating_sub(state.frame())), Color::WHITE),
+            ("SCORE", score.points().to_string(), Color::WHITE),
+            ("LINES", score.lines().to_string(), Color::WHITE),
+        ],
+        GameMode::Sprint { lines } => {
+            let (split, color) = match personal_best.and_then(|record| record.delta(state.splits())) {
+                Some(delta) => (mode::format_delta(delta), if delta <= 0 { AHEAD_COLOR } else { BEHIND_COLOR }),
+                None => ("-".to_string(), Color::WHITE),
+            };
+            vec![
+                ("TIME", mode::format_time(state.frame()), Color::WHITE),
+                ("LINES", format!("{}/{}", score.lines(), lines), Color::WHITE),
+                ("SPLIT", split, color),
+            ]
+        },
+    };
+    let mut top = 6f32 * crate::TILE_SIZE as f32;
+    for (label, value, color) in stats {
+        for (text, scale, color) in [(label, 20f32, Color::WHITE), (value.as_str(), 24f32, color)] {
+            let mut text = graphics::Text::new(text);
+            text.set_layout(graphics::TextLayout::center());
+            text.set_scale(scale);
+            canvas.draw(&text, graphics::DrawParam::from([center_x, top]).color(color));
+            top += crate::TILE_SIZE as f32 * 1.25f32;
+        }
+        top += crate::TILE_SIZE as f32 * 0.5f32;
+    }
+}
+
+fn draw_callout(layout: &Layout, state: &GameState, canvas: &mut graphics::Canvas) {
+    let event = match state.last_lock() {
+        Some(event) if state.frame() - event.frame < CALLOUT_FRAMES => event,
+        _ => return,
+    };
+    let mut top = 15f32 * crate::TILE_SIZE as f32;
+    for line in callout_lines(&event) {
+        let mut text = graphics::Text::new(line);
+        text.set_layout(graphics::TextLayout::center());
+        text.set_scale(16f32);
+        canvas.draw(&text, graphics::DrawParam::from([layout.board_left() / 2f32, top]).color(Color::WHITE));
+        top += crate::TILE_SIZE as f32;
+    }
+}
+
+fn callout_lines(event: &LockEvent) -> Vec<String> {
+    let clear = &event.clear;
+    let mut lines = Vec::new();
+    if event.back_to_back {
+        lines.push("BACK-TO-BACK".to_string());
+    }
+    lines.extend(clear.name());
+    if let Some(combo) = event.combo.filter(|&combo| combo > 0) {
+        lines.push(format!("{} COMBO", combo));
+    }
+    if clear.perfect_clear {
+        lines.push("PERFECT CLEAR".to_string());
+    }
+    lines
+}
+
+fn draw_next_queue(layout: &Layout, state: &GameState, canvas: &mut graphics::Canvas) {
+    let center_x = draw_panel(layout, "NEXT", layout.board_left() + (layout.board_width * crate::TILE_SIZE) as f32, canvas);
+    let mut top = 2f32 * crate::TILE_SIZE as f32;
+    for kind in state.next_queue() {
+        draw_preview(&state.preview(*kind), kind_color(*kind), center_x, top, canvas);
+        top += 3f32 * PREVIEW_TILE_SIZE;
+    }
+}
+
+/// Draws the background and the label of a side panel starting at `left`, returns the panel's horizontal center.
+fn draw_panel(layout: &Layout, label: &str, left: f32, canvas: &mut graphics::Canvas) -> f32 {
+    let width = (layout.side_panel_width * crate::TILE_SIZE) as f32;
+    canvas.draw(
+        &graphics::Quad,
+        graphics::DrawParam::new()
+            .dest_rect(graphics::Rect::new(left, 0f32, width, layout.screen_size().1))
+            .color(PANEL_COLOR)
+    );
+
+    let center_x = left + width / 2f32;
+    let mut label = graphics::Text::new(label);
+    label.set_layout(graphics::TextLayout::center());
+    label.set_scale(20f32);
+    canvas.draw(&label, graphics::DrawParam::from([center_x, crate::TILE_SIZE as f32]).color(Color::WHITE));
+    center_x
+}
+
+/// Draws a small version of the tetromino, horizontally centered on `center_x`, skipping empty rows of its shape.
+fn draw_preview(shape: &[Vec<bool>], color: Color, center_x: f32, top: f32, canvas: &mut graphics::Canvas) {
+    let rows: Vec<&Vec<bool>> = shape.iter().filter(|row| row.iter().any(|x| *x)).collect();
+    let columns = (0..shape[0].len()).filter(|&x| rows.iter().any(|row| row[x])).collect::<Vec<usize>>();
+    let left = center_x - columns.len() as f32 * PREVIEW_TILE_SIZE / 2f32;
+    for (y, row) in rows.iter().enumerate() {
+        for (x, column) in columns.iter().enumerate() {
+            if row[*column] {
+                canvas.draw(
+                    &graphics::Quad,
+                    graphics::DrawParam::new()
+                        .dest_rect(graphics::Rect::new(
+                            left + x as f32 * PREVIEW_TILE_SIZE,
+                            top + y as f32 * PREVIEW_TILE_SIZE,
+                            PREVIEW_TILE_SIZE,
+                            PREVIEW_TILE_SIZE,
+                        ))
+                        .color(color)
+                );
+            }
+        }
+    }
+}
+
+fn draw_tile(layout: &Layout, tile: &BoardTile, color: Color, canvas: &mut graphics::Canvas) {
+    canvas.draw(
+        &graphics::Quad,
+        graphics::DrawParam::new()
+            .dest_rect(tile_rect(layout, tile))
+            .color(color)
+    );
+}
+
+fn draw_text(layout: &Layout, text: &str, height_percentage: f32, canvas: &mut graphics::Canvas) {
+    draw_scaled_text(layout, text, 32f32, height_percentage, canvas);
+}
+
+fn draw_scaled_text(layout: &Layout, text: &str, scale: f32, height_percentage: f32, canvas: &mut graphics::Canvas) {
+    let mut text = graphics::Text::new(text);
+    text.set_layout(graphics::TextLayout::center());
+    text.set_scale(scale);
+    let width = layout.board_left() + (layout.board_width * crate::TILE_SIZE) as f32 / 2f32;
+    let height = ((BUFFER_ROWS as f32 + layout.board_height as f32 * height_percentage) * crate::TILE_SIZE as f32).round();
+    let game_draw_param = graphics::DrawParam::from([width, height]).color(Color::WHITE);
+    canvas.draw(&text, game_draw_param);
+}
+
+fn tile_rect(layout: &Layout, tile: &BoardTile) -> graphics::Rect {
+    graphics::Rect::new(
+        layout.board_left() + (tile.x as usize * crate::TILE_SIZE) as f32,
+        (tile.y as usize * crate::TILE_SIZE) as f32,
+        crate::TILE_SIZE as f32,
+        crate::TILE_SIZE as f32,
+    )
+}
+
+/// Color of a tile of a row being cleared, at the current frame of the line clear animation.
+fn clearing_color(state: &GameState, tile: &BoardTile) -> Color {
+    let delays = state.settings().delays;
+    match delays.animation.tile(tile.x as usize, state.width(), state.phase_frames(), delays.line_clear) {
+        ClearingTile::White => Color::WHITE,
+        // faded towards the black of empty tiles
+        ClearingTile::Faded(opacity) => {
+            let color = tile_color(tile);
+            Color::new(color.r * opacity, color.g * opacity, color.b * opacity, 1f32)
+        },
+    }
+}
+
+fn tile_color(tile: &BoardTile) -> Color {
+    match tile.kind {
+        Some(kind) => kind_color(kind),
+        None => Color::BLACK,
+    }
+}
+
+fn kind_color(kind: TetrominoKind) -> Color {
+    match kind {
+        TetrominoKind::I => Color::CYAN,
+        TetrominoKind::O => Color::YELLOW,
+        TetrominoKind::T => Color::from_rgb(0xa0, 0x20, 0xf0), //Purple,
+        TetrominoKind::S => Color::GREEN,
+        TetrominoKind::Z => Color::RED,
+        TetrominoKind::J => Color::BLUE,
+        TetrominoKind::L => Color::from_rgb(0xff, 0xa5, 0x00), //Orange,
+    }
+}
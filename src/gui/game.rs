@@ -0,0 +1,42 @@
+use tetris_rs::{
+    action::Action,
+    mode::GameMode,
+    personal_best::{PersonalBests, SprintRecord},
+    replay::Recorder,
+    GameSettings, GameState,
+};
+
+/// Game played from the keyboard, its input is recorded so it can be saved as a replay.
+pub struct Game {
+    pub state: GameState,
+    pub recorder: Recorder,
+    /// Record the game is compared against, taken before the game started.
+    pub personal_best: Option<SprintRecord>,
+}
+
+impl Game {
+    pub fn new(seed: u64, settings: GameSettings, personal_bests: &PersonalBests) -> Self {
+        let state = GameState::new(seed, settings);
+        let personal_best = match settings.mode {
+            GameMode::Sprint { lines } => personal_bests.sprint(lines).cloned(),
+            GameMode::Endless | GameMode::Ultra => None,
+        };
+        Self {
+            recorder: Recorder::new(&state),
+            state,
+            personal_best,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.recorder.update(&mut self.state);
+    }
+
+    pub fn key_down(&mut self, action: Action) {
+        self.recorder.key_down(&mut self.state, action);
+    }
+
+    pub fn key_up(&mut self, action: Action) {
+        self.recorder.key_up(&mut self.state, action);
+    }
+}
@@ -0,0 +1,113 @@
+use ggez::input::gamepad::{gilrs, GamepadId};
+use tetris_rs::controls::Button;
+
+/// Button of the engine for a gilrs button.
+fn button(button: gilrs::Button) -> Option<Button> {
+    match button {
+        gilrs::Button::South => Some(Button::South),
+        gilrs::Button::East => Some(Button::East),
+        gilrs::Button::North => Some(Button::North),
+        gilrs::Button::West => Some(Button::West),
+        gilrs::Button::LeftTrigger => Some(Button::LeftTrigger),
+        gilrs::Button::LeftTrigger2 => Some(Button::LeftTrigger2),
+        gilrs::Button::RightTrigger => Some(Button::RightTrigger),
+        gilrs::Button::RightTrigger2 => Some(Button::RightTrigger2),
+        gilrs::Button::Select => Some(Button::Select),
+        gilrs::Button::Start => Some(Button::Start),
+        gilrs::Button::LeftThumb => Some(Button::LeftThumb),
+        gilrs::Button::RightThumb => Some(Button::RightThumb),
+        gilrs::Button::DPadUp => Some(Button::DPadUp),
+        gilrs::Button::DPadDown => Some(Button::DPadDown),
+        gilrs::Button::DPadLeft => Some(Button::DPadLeft),
+        gilrs::Button::DPadRight => Some(Button::DPadRight),
+        _ => None,
+    }
+}
+
+/// Buttons of the negative and the positive direction of an axis; gilrs reports up as positive.
+fn directions(axis: gilrs::Axis) -> Option<(Button, Button)> {
+    match axis {
+        gilrs::Axis::LeftStickX => Some((Button::LeftStickLeft, Button::LeftStickRight)),
+        gilrs::Axis::LeftStickY => Some((Button::LeftStickDown, Button::LeftStickUp)),
+        gilrs::Axis::RightStickX => Some((Button::RightStickLeft, Button::RightStickRight)),
+        gilrs::Axis::RightStickY => Some((Button::RightStickDown, Button::RightStickUp)),
+        gilrs::Axis::DPadX => Some((Button::DPadLeft, Button::DPadRight)),
+        gilrs::Axis::DPadY => Some((Button::DPadDown, Button::DPadUp)),
+        _ => None,
+    }
+}
+
+/// Some pads report the D-pad both as buttons and as axes, a direction counts as pressed while either source holds it.
+struct Pad {
+    id: GamepadId,
+    /// Axis directions currently pushed past the deadzone.
+    pushed: Vec<Button>,
+    /// Buttons currently held.
+    held: Vec<Button>,
+}
+
+impl Pad {
+    fn is_pressed(&self, button: Button) -> bool {
+        self.pushed.contains(&button) || self.held.contains(&button)
+    }
+
+    /// Applies `update` to the state of `button`'s source, returns the new state of the button when it changed.
+    fn update(&mut self, button: Button, update: impl FnOnce(&mut Self)) -> Option<bool> {
+        let was_pressed = self.is_pressed(button);
+        update(self);
+        let is_pressed = self.is_pressed(button);
+        (was_pressed != is_pressed).then_some(is_pressed)
+    }
+}
+
+fn set(buttons: &mut Vec<Button>, button: Button, pressed: bool) {
+    buttons.retain(|other| *other != button);
+    if pressed {
+        buttons.push(button);
+    }
+}
+
+/// Gamepads that have sent input, numbered in the order of their first input so that players can be told apart.
+#[derive(Default)]
+pub struct Gamepads {
+    pads: Vec<Pad>,
+}
+
+impl Gamepads {
+    pub fn count(&self) -> usize {
+        self.pads.len()
+    }
+
+    /// Number of the pad, from 0; a pad seen for the first time gets the next one.
+    pub fn player(&mut self, id: GamepadId) -> usize {
+        match self.pads.iter().position(|pad| pad.id == id) {
+            Some(player) => player,
+            None => {
+                self.pads.push(Pad { id, pushed: Vec::new(), held: Vec::new() });
+                self.pads.len() - 1
+            },
+        }
+    }
+
+    /// Turns a press (`true`) or release (`false`) of a gilrs button into the change of the engine's button, `None`
+    /// when the button is unknown or already in that state.
+    pub fn button(&mut self, id: GamepadId, button: gilrs::Button, pressed: bool) -> Option<(Button, bool)> {
+        let button = self::button(button)?;
+        let player = self.player(id);
+        let pressed = self.pads[player].update(button, |pad| set(&mut pad.held, button, pressed))?;
+        Some((button, pressed))
+    }
+
+    /// Turns a new value of an axis into presses (`true`) and releases (`false`) of its direction buttons.
+    pub fn axis(&mut self, id: GamepadId, axis: gilrs::Axis, value: f32, deadzone: f32) -> Vec<(Button, bool)> {
+        let (negative, positive) = match directions(axis) {
+            Some(directions) => directions,
+            None => return Vec::new(),
+        };
+        let player = self.player(id);
+        let pad = &mut self.pads[player];
+        [(negative, value <= -deadzone), (positive, value >= deadzone)].into_iter()
+            .filter_map(|(button, pushed)| pad.update(button, |pad| set(&mut pad.pushed, button, pushed)).map(|pressed| (button, pressed)))
+            .collect()
+    }
+}
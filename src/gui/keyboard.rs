@@ -0,0 +1,112 @@
+use ggez::input::keyboard::KeyCode;
+use tetris_rs::controls::Key;
+
+/// Translates a ggez key code into the key names used by the key bindings.
+pub fn key(keycode: KeyCode) -> Option<Key> {
+    let key = match keycode {
+        KeyCode::Key0 => Key::Char('0'),
+        KeyCode::Key1 => Key::Char('1'),
+        KeyCode::Key2 => Key::Char('2'),
+        KeyCode::Key3 => Key::Char('3'),
+        KeyCode::Key4 => Key::Char('4'),
+        KeyCode::Key5 => Key::Char('5'),
+        KeyCode::Key6 => Key::Char('6'),
+        KeyCode::Key7 => Key::Char('7'),
+        KeyCode::Key8 => Key::Char('8'),
+        KeyCode::Key9 => Key::Char('9'),
+        KeyCode::A => Key::Char('A'),
+        KeyCode::B => Key::Char('B'),
+        KeyCode::C => Key::Char('C'),
+        KeyCode::D => Key::Char('D'),
+        KeyCode::E => Key::Char('E'),
+        KeyCode::F => Key::Char('F'),
+        KeyCode::G => Key::Char('G'),
+        KeyCode::H => Key::Char('H'),
+        KeyCode::I => Key::Char('I'),
+        KeyCode::J => Key::Char('J'),
+        KeyCode::K => Key::Char('K'),
+        KeyCode::L => Key::Char('L'),
+        KeyCode::M => Key::Char('M'),
+        KeyCode::N => Key::Char('N'),
+        KeyCode::O => Key::Char('O'),
+        KeyCode::P => Key::Char('P'),
+        KeyCode::Q => Key::Char('Q'),
+        KeyCode::R => Key::Char('R'),
+        KeyCode::S => Key::Char('S'),
+        KeyCode::T => Key::Char('T'),
+        KeyCode::U => Key::Char('U'),
+        KeyCode::V => Key::Char('V'),
+        KeyCode::W => Key::Char('W'),
+        KeyCode::X => Key::Char('X'),
+        KeyCode::Y => Key::Char('Y'),
+        KeyCode::Z => Key::Char('Z'),
+        KeyCode::Apostrophe => Key::Char('\''),
+        KeyCode::Backslash => Key::Char('\\'),
+        KeyCode::Comma => Key::Char(','),
+        KeyCode::Equals => Key::Char('='),
+        KeyCode::Grave => Key::Char('`'),
+        KeyCode::LBracket => Key::Char('['),
+        KeyCode::Minus => Key::Char('-'),
+        KeyCode::Period => Key::Char('.'),
+        KeyCode::RBracket => Key::Char(']'),
+        KeyCode::Semicolon => Key::Char(';'),
+        KeyCode::Slash => Key::Char('/'),
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Space => Key::Space,
+        KeyCode::Return | KeyCode::NumpadEnter => Key::Enter,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Back => Key::Backspace,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::LShift => Key::LShift,
+        KeyCode::RShift => Key::RShift,
+        KeyCode::LControl => Key::LControl,
+        KeyCode::RControl => Key::RControl,
+        KeyCode::LAlt => Key::LAlt,
+        KeyCode::RAlt => Key::RAlt,
+        KeyCode::Numpad0 => Key::Numpad(0),
+        KeyCode::Numpad1 => Key::Numpad(1),
+        KeyCode::Numpad2 => Key::Numpad(2),
+        KeyCode::Numpad3 => Key::Numpad(3),
+        KeyCode::Numpad4 => Key::Numpad(4),
+        KeyCode::Numpad5 => Key::Numpad(5),
+        KeyCode::Numpad6 => Key::Numpad(6),
+        KeyCode::Numpad7 => Key::Numpad(7),
+        KeyCode::Numpad8 => Key::Numpad(8),
+        KeyCode::Numpad9 => Key::Numpad(9),
+        KeyCode::F1 => Key::F(1),
+        KeyCode::F2 => Key::F(2),
+        KeyCode::F3 => Key::F(3),
+        KeyCode::F4 => Key::F(4),
+        KeyCode::F5 => Key::F(5),
+        KeyCode::F6 => Key::F(6),
+        KeyCode::F7 => Key::F(7),
+        KeyCode::F8 => Key::F(8),
+        KeyCode::F9 => Key::F(9),
+        KeyCode::F10 => Key::F(10),
+        KeyCode::F11 => Key::F(11),
+        KeyCode::F12 => Key::F(12),
+        KeyCode::F13 => Key::F(13),
+        KeyCode::F14 => Key::F(14),
+        KeyCode::F15 => Key::F(15),
+        KeyCode::F16 => Key::F(16),
+        KeyCode::F17 => Key::F(17),
+        KeyCode::F18 => Key::F(18),
+        KeyCode::F19 => Key::F(19),
+        KeyCode::F20 => Key::F(20),
+        KeyCode::F21 => Key::F(21),
+        KeyCode::F22 => Key::F(22),
+        KeyCode::F23 => Key::F(23),
+        KeyCode::F24 => Key::F(24),
+        _ => return None,
+    };
+    Some(key)
+}
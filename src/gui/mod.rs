@@ -0,0 +1,596 @@
+mod draw;
+mod game;
+mod gamepad;
+mod keyboard;
+mod settings;
+
+use std::{path::PathBuf, time::Duration};
+
+use ggez::{
+    event, graphics,
+    input::{
+        gamepad::{gilrs, GamepadId},
+        keyboard::{KeyCode, KeyInput},
+    },
+    Context,
+};
+use tetris_rs::{
+    action::Action,
+    config::{Config, Gamepad},
+    controls::{Button, Control, KeyBindings},
+    high_scores::HighScores,
+    mode::GameMode,
+    options::Options,
+    personal_best::PersonalBests,
+    replay::Playback,
+    GameSettings,
+};
+
+use game::Game;
+use gamepad::Gamepads;
+
+pub use draw::Layout;
+
+/// Number of frames the replay is rewound by with the left arrow.
+const SEEK_FRAMES: u64 = 5 * tetris_rs::FRAMES_PER_SECOND;
+
+/// Modes offered on the mode select and leaderboard screens.
+const MODES: [GameMode; 5] = [
+    GameMode::Endless,
+    GameMode::Sprint { lines: 20 },
+    GameMode::Sprint { lines: 40 },
+    GameMode::Sprint { lines: 100 },
+    GameMode::Ultra,
+];
+const TITLE_ITEMS: [&str; 5] = ["PLAY", "HIGH SCORES", "SETTINGS", "CONTROLS", "QUIT"];
+const PAUSE_ITEMS: [&str; 3] = ["RESUME", "RETRY", "MAIN MENU"];
+
+/// A key press, or a press of a gamepad button which in menus acts as the key with the same role (see `menu_key`).
+#[derive(Clone, Copy, PartialEq)]
+enum Press {
+    Key(KeyCode),
+    Button(Button),
+}
+
+/// Screens of the application, each one handles the keyboard on its own.
+enum Scene {
+    Title { selected: usize },
+    ModeSelect { selected: usize },
+    /// `selected` may point one past `settings::ITEMS`, at the back item.
+    Settings { selected: usize },
+    /// `selected` may point one past `Control::ALL`, at the back item; while `waiting` the next key or button is bound
+    /// to the selected control. `message` reports a conflicting key.
+    Controls { selected: usize, waiting: bool, message: Option<String>, changed: bool },
+    Playing(Game),
+    Paused { game: Game, selected: usize },
+    /// The game made it to the leaderboard at `rank`, the player types their name.
+    NameEntry { game: Game, name: String, rank: usize },
+    /// The game topped out.
+    GameOver(Game),
+    /// The game reached the goal or the time limit of its mode.
+    Results(Game),
+    /// High scores of `MODES[mode]`, `highlight` is the rank of the entry just added.
+    Leaderboard { mode: usize, highlight: Option<usize>, after_game: bool },
+    Replay(Playback),
+}
+
+/// ggez front end, a state machine of scenes on top of the engine.
+pub struct App {
+    scene: Scene,
+    layout: Layout,
+    /// Shortest time between two redraws; the game logic always runs at `FRAMES_PER_SECOND`.
+    render_interval: Duration,
+    /// Time (since the start) the next redraw is due at.
+    next_render: Duration,
+    keys: KeyBindings,
+    gamepad: Gamepad,
+    gamepads: Gamepads,
+    /// Configuration file changed key bindings are saved to.
+    config: Option<PathBuf>,
+    /// Settings new games are started with, the mode is picked on the mode select screen.
+    settings: GameSettings,
+    /// Seed of the next game, games after the first one get a random seed.
+    seed: Option<u64>,
+    /// File the replay of the latest game is saved to.
+    record: Option<PathBuf>,
+    personal_bests: PersonalBests,
+    high_scores: HighScores,
+    /// Name suggested on the name entry screen, the last entered one.
+    player_name: String,
+}
+
+impl App {
+    pub fn new(layout: Layout, options: Options) -> Self {
+        let personal_bests = PersonalBests::load().unwrap_or_else(|e| {
+            eprintln!("{}, personal bests are reset", e);
+            PersonalBests::default()
+        });
+        let high_scores = HighScores::load().unwrap_or_else(|e| {
+            eprintln!("{}, high scores are reset", e);
+            HighScores::default()
+        });
+        Self {
+            scene: Scene::Title { selected: 0 },
+            layout,
+            render_interval: Duration::from_secs(1) / options.display.fps,
+            next_render: Duration::ZERO,
+            keys: options.keys,
+            gamepad: options.gamepad,
+            gamepads: Gamepads::default(),
+            config: options.config,
+            settings: options.settings,
+            seed: Some(options.seed),
+            record: options.record,
+            personal_bests,
+            high_scores,
+            player_name: default_player_name(),
+        }
+    }
+
+    pub fn replay(layout: Layout, options: Options, playback: Playback) -> Self {
+        let mut app = Self::new(layout, Options { record: None, ..options });
+        app.scene = Scene::Replay(playback);
+        app
+    }
+
+    /// Control bound to the key or the button.
+    fn control(&self, press: Press) -> Option<Control> {
+        match press {
+            Press::Key(keycode) => keyboard::key(keycode).and_then(|key| self.keys.control(key)),
+            Press::Button(button) => self.gamepad.buttons.control(button),
+        }
+    }
+
+    /// Whether the press opens (or closes) the pause menu: the pause control, or Escape unless it is bound to something else.
+    fn is_pause(&self, press: Press) -> bool {
+        match self.control(press) {
+            Some(control) => control == Control::Action(Action::Pause),
+            None => menu_key(press) == Some(KeyCode::Escape),
+        }
+    }
+
+    /// First key bound to the control, as shown in hints.
+    fn key_name(&self, control: Control) -> String {
+        self.keys.inputs(control).first().map_or("-".to_string(), |key| key.to_string())
+    }
+
+    fn save_bindings(&self) {
+        match &self.config {
+            Some(path) => match Config::save_bindings(path, &self.keys, &self.gamepad.buttons) {
+                Ok(()) => println!("key and button bindings saved to {}", path.display()),
+                Err(e) => eprintln!("{}", e),
+            },
+            None => eprintln!("can not determine the configuration file, key and button bindings are not saved"),
+        }
+    }
+
+    fn new_game(&mut self) -> Scene {
+        let seed = self.seed.take().unwrap_or_else(rand::random);
+        println!("seed: {}", seed);
+        Scene::Playing(Game::new(seed, self.settings, &self.personal_bests))
+    }
+
+    /// Records the result of the ended game and moves on to the name entry, game over or results screen.
+    fn end_game(&mut self, game: Game) -> Scene {
+        self.save_replay(&game);
+        if self.personal_bests.submit(&game.state) {
+            if let Err(e) = self.personal_bests.save() {
+                eprintln!("{}", e);
+            }
+        }
+        match self.high_scores.rank(&game.state) {
+            Some(rank) => Scene::NameEntry { game, name: self.player_name.clone(), rank },
+            None => ended(game),
+        }
+    }
+
+    fn save_replay(&self, game: &Game) {
+        if let Some(path) = &self.record {
+            match game.recorder.replay().save(path) {
+                Ok(()) => println!("replay saved to {}", path.display()),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+
+    fn leaderboard(&self, highlight: Option<usize>, after_game: bool) -> Scene {
+        let mode = MODES.iter().position(|mode| *mode == self.settings.mode).unwrap_or(0);
+        Scene::Leaderboard { mode, highlight, after_game }
+    }
+
+    fn press(&mut self, ctx: &mut Context, press: Press) {
+        let scene = std::mem::replace(&mut self.scene, Scene::Title { selected: 0 });
+        self.scene = self.next_scene(ctx, scene, press);
+    }
+
+    fn release(&mut self, press: Press) {
+        // releases are forwarded while paused too, otherwise keys held when pausing would stay pressed
+        let control = self.control(press);
+        if let (Scene::Playing(game) | Scene::Paused { game, .. }, Some(Control::Action(action))) = (&mut self.scene, control) {
+            game.key_up(action);
+        }
+    }
+
+    /// Numbers the pad on its first input; every pad controls the one game for now, the numbers are there to tell
+    /// players apart once there is more than one.
+    fn connect(&mut self, ctx: &Context, id: GamepadId) {
+        let count = self.gamepads.count();
+        let player = self.gamepads.player(id);
+        if player == count {
+            println!("gamepad {}: {}", player + 1, ctx.gamepad.gamepad(id).name());
+        }
+    }
+
+    /// Handles a key or button press in `scene`, returns the scene to show next.
+    fn next_scene(&mut self, ctx: &mut Context, scene: Scene, press: Press) -> Scene {
+        let keycode = menu_key(press);
+        match scene {
+            Scene::Title { selected } => match keycode {
+                Some(KeyCode::Return | KeyCode::Space) => match selected {
+                    0 => Scene::ModeSelect { selected: MODES.iter().position(|mode| *mode == self.settings.mode).unwrap_or(0) },
+                    1 => self.leaderboard(None, false),
+                    2 => Scene::Settings { selected: 0 },
+                    3 => Scene::Controls { selected: 0, waiting: false, message: None, changed: false },
+                    _ => {
+                        ctx.request_quit();
+                        scene
+                    },
+                },
+                Some(KeyCode::Escape) => {
+                    ctx.request_quit();
+                    scene
+                },
+                _ => Scene::Title { selected: navigate(selected, TITLE_ITEMS.len(), keycode) },
+            },
+            Scene::ModeSelect { selected } => match keycode {
+                Some(KeyCode::Return | KeyCode::Space) => {
+                    self.settings.mode = MODES[selected];
+                    self.new_game()
+                },
+                Some(KeyCode::Escape) => Scene::Title { selected: 0 },
+                _ => Scene::ModeSelect { selected: navigate(selected, MODES.len(), keycode) },
+            },
+            Scene::Settings { selected } => match keycode {
+                Some(KeyCode::Left | KeyCode::Right) if selected < settings::ITEMS.len() => {
+                    settings::change(&mut self.settings, selected, if keycode == Some(KeyCode::Left) { -1 } else { 1 });
+                    scene
+                },
+                Some(KeyCode::Return | KeyCode::Space) if selected == settings::ITEMS.len() => Scene::Title { selected: 2 },
+                Some(KeyCode::Escape) => Scene::Title { selected: 2 },
+                _ => Scene::Settings { selected: navigate(selected, settings::ITEMS.len() + 1, keycode) },
+            },
+            Scene::Controls { selected, waiting: true, changed, .. } => {
+                let control = Control::ALL[selected];
+                let bound = match press {
+                    Press::Key(KeyCode::Escape) => return Scene::Controls { selected, waiting: false, message: None, changed },
+                    Press::Key(keycode) => match keyboard::key(keycode) {
+                        Some(key) => self.keys.bind(control, key).map_err(|bound| (key.to_string(), bound)),
+                        None => return scene,
+                    },
+                    Press::Button(button) => self.gamepad.buttons.bind(control, button).map_err(|bound| (button.to_string(), bound)),
+                };
+                match bound {
+                    Ok(()) => Scene::Controls { selected, waiting: false, message: None, changed: true },
+                    Err((input, bound)) => {
+                        let message = format!("{} IS ALREADY BOUND TO {}", input.to_uppercase(), control_label(bound));
+                        Scene::Controls { selected, waiting: false, message: Some(message), changed }
+                    },
+                }
+            },
+            Scene::Controls { selected, changed, .. } => match keycode {
+                Some(KeyCode::Return | KeyCode::Space) if selected < Control::ALL.len() => {
+                    Scene::Controls { selected, waiting: true, message: None, changed }
+                },
+                Some(KeyCode::Back | KeyCode::Delete) if selected < Control::ALL.len() => {
+                    self.keys.clear(Control::ALL[selected]);
+                    self.gamepad.buttons.clear(Control::ALL[selected]);
+                    Scene::Controls { selected, waiting: false, message: None, changed: true }
+                },
+                Some(KeyCode::Return | KeyCode::Space | KeyCode::Escape) => {
+                    if changed {
+                        self.save_bindings();
+                    }
+                    Scene::Title { selected: 3 }
+                },
+                _ => Scene::Controls { selected: navigate(selected, Control::ALL.len() + 1, keycode), waiting: false, message: None, changed },
+            },
+            Scene::Playing(mut game) => match self.control(press) {
+                _ if self.is_pause(press) => Scene::Paused { game, selected: 0 },
+                Some(Control::Action(action)) => {
+                    game.key_down(action);
+                    Scene::Playing(game)
+                },
+                Some(Control::Restart) => {
+                    self.save_replay(&game);
+                    self.new_game()
+                },
+                Some(Control::Quit) => {
+                    ctx.request_quit();
+                    Scene::Playing(game)
+                },
+                None => Scene::Playing(game),
+            },
+            Scene::Paused { game, selected } => match keycode {
+                _ if self.is_pause(press) => Scene::Playing(game),
+                _ if self.control(press) == Some(Control::Restart) => {
+                    self.save_replay(&game);
+                    self.new_game()
+                },
+                _ if self.control(press) == Some(Control::Quit) => {
+                    ctx.request_quit();
+                    Scene::Paused { game, selected }
+                },
+                Some(KeyCode::Return | KeyCode::Space) => match selected {
+                    0 => Scene::Playing(game),
+                    1 => {
+                        self.save_replay(&game);
+                        self.new_game()
+                    },
+                    _ => {
+                        self.save_replay(&game);
+                        Scene::Title { selected: 0 }
+                    },
+                },
+                _ => Scene::Paused { game, selected: navigate(selected, PAUSE_ITEMS.len(), keycode) },
+            },
+            Scene::NameEntry { game, mut name, rank } => match keycode {
+                Some(KeyCode::Back) => {
+                    name.pop();
+                    Scene::NameEntry { game, name, rank }
+                },
+                Some(KeyCode::Return | KeyCode::NumpadEnter) => {
+                    self.high_scores.insert(&game.state, &name);
+                    if let Err(e) = self.high_scores.save() {
+                        eprintln!("{}", e);
+                    }
+                    self.player_name = name;
+                    self.leaderboard(Some(rank), true)
+                },
+                Some(KeyCode::Escape) => ended(game),
+                _ => Scene::NameEntry { game, name, rank },
+            },
+            Scene::GameOver(_) | Scene::Results(_) => match keycode {
+                _ if self.control(press) == Some(Control::Restart) => self.new_game(),
+                _ if self.control(press) == Some(Control::Quit) => {
+                    ctx.request_quit();
+                    scene
+                },
+                Some(KeyCode::L) => self.leaderboard(None, true),
+                Some(KeyCode::Escape | KeyCode::Return) => Scene::Title { selected: 0 },
+                _ => scene,
+            },
+            Scene::Leaderboard { mode, highlight, after_game } => match keycode {
+                Some(KeyCode::Left) => Scene::Leaderboard { mode: (mode + MODES.len() - 1) % MODES.len(), highlight: None, after_game },
+                Some(KeyCode::Right) => Scene::Leaderboard { mode: (mode + 1) % MODES.len(), highlight: None, after_game },
+                _ if after_game && self.control(press) == Some(Control::Restart) => self.new_game(),
+                Some(KeyCode::Escape | KeyCode::Return) => Scene::Title { selected: if after_game { 0 } else { 1 } },
+                _ => Scene::Leaderboard { mode, highlight, after_game },
+            },
+            Scene::Replay(mut playback) => {
+                match keycode {
+                    Some(KeyCode::Escape) => ctx.request_quit(),
+                    Some(KeyCode::Space | KeyCode::P) => playback.toggle_pause(),
+                    Some(KeyCode::Right | KeyCode::Period) => playback.step(),
+                    Some(KeyCode::Up) => playback.faster(),
+                    Some(KeyCode::Down) => playback.slower(),
+                    Some(KeyCode::Left) => playback.seek(playback.frame().saturating_sub(SEEK_FRAMES)),
+                    Some(KeyCode::Home) => playback.seek(0),
+                    _ => {}
+                }
+                Scene::Replay(playback)
+            },
+        }
+    }
+}
+
+/// Screen shown for a game that has ended.
+fn ended(game: Game) -> Scene {
+    if game.state.is_game_over() {
+        Scene::GameOver(game)
+    } else {
+        Scene::Results(game)
+    }
+}
+
+/// Name of the control as listed on the controls screen.
+fn control_label(control: Control) -> String {
+    control.name().replace('_', " ").to_uppercase()
+}
+
+/// Key a press acts as in menus: directions of the D-pad and the left stick navigate, South confirms and East goes back.
+fn menu_key(press: Press) -> Option<KeyCode> {
+    match press {
+        Press::Key(keycode) => Some(keycode),
+        Press::Button(Button::DPadUp | Button::LeftStickUp) => Some(KeyCode::Up),
+        Press::Button(Button::DPadDown | Button::LeftStickDown) => Some(KeyCode::Down),
+        Press::Button(Button::DPadLeft | Button::LeftStickLeft) => Some(KeyCode::Left),
+        Press::Button(Button::DPadRight | Button::LeftStickRight) => Some(KeyCode::Right),
+        Press::Button(Button::South | Button::Start) => Some(KeyCode::Return),
+        Press::Button(Button::East) => Some(KeyCode::Escape),
+        Press::Button(_) => None,
+    }
+}
+
+/// Moves the selection of a menu with `count` items up or down, wrapping around.
+fn navigate(selected: usize, count: usize, keycode: Option<KeyCode>) -> usize {
+    match keycode {
+        Some(KeyCode::Up) => (selected + count - 1) % count,
+        Some(KeyCode::Down) => (selected + 1) % count,
+        _ => selected,
+    }
+}
+
+fn default_player_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .map(|name| name.chars().take(HighScores::MAX_NAME_LENGTH).collect())
+        .unwrap_or_default()
+}
+
+impl event::EventHandler<ggez::GameError> for App {
+    fn update(&mut self, ctx: &mut Context) -> std::prelude::v1::Result<(), ggez::GameError> {
+        while ctx.time.check_update_time(tetris_rs::FRAMES_PER_SECOND as u32) {
+            match &mut self.scene {
+                Scene::Playing(game) => {
+                    game.update();
+                    if game.state.has_ended() {
+                        if let Scene::Playing(game) = std::mem::replace(&mut self.scene, Scene::Title { selected: 0 }) {
+                            self.scene = self.end_game(game);
+                        }
+                    }
+                },
+                Scene::Replay(playback) => playback.update(),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> std::prelude::v1::Result<(), ggez::GameError> {
+            // ggez draws after every update, skipping the canvas until the next render caps the redraw rate and keeps
+            // the last frame on screen
+            let now = ctx.time.time_since_start();
+            if now < self.next_render {
+                ggez::timer::yield_now();
+                return Ok(());
+            }
+            self.next_render = self.next_render.max(now) + self.render_interval;
+
+            let mut canvas = graphics::Canvas::from_frame(ctx, graphics::Color::from([0.0, 0.0, 0.0, 1.0]));
+            let (width, height) = self.layout.screen_size();
+            canvas.set_screen_coordinates(graphics::Rect::new(0f32, 0f32, width, height));
+    
+            match &self.scene {
+                Scene::Title { selected } => {
+                    let items = TITLE_ITEMS.map(|item| item.to_string());
+                    draw::draw_menu(&self.layout, "TETRIS", &items, *selected, &mut canvas);
+                    draw::draw_hint(&self.layout, &format!("mode: {}", self.settings.mode.name()), &mut canvas);
+                },
+                Scene::ModeSelect { selected } => {
+                    let items = MODES.map(|mode| mode.name().to_uppercase());
+                    draw::draw_menu(&self.layout, "MODE", &items, *selected, &mut canvas);
+                    draw::draw_hint(&self.layout, "Enter - play   Esc - back", &mut canvas);
+                },
+                Scene::Settings { selected } => {
+                    let items: Vec<String> = settings::ITEMS.iter().enumerate()
+                        .map(|(index, item)| format!("{}  < {} >", item, settings::value(&self.settings, index)))
+                        .chain(std::iter::once("BACK".to_string()))
+                        .collect();
+                    draw::draw_menu(&self.layout, "SETTINGS", &items, *selected, &mut canvas);
+                    draw::draw_hint(&self.layout, "Left / Right - change   Esc - back", &mut canvas);
+                },
+                Scene::Controls { selected, waiting, message, .. } => {
+                    let items: Vec<String> = Control::ALL.iter().enumerate()
+                        .map(|(index, control)| {
+                            let inputs: Vec<String> = self.keys.inputs(*control).iter().map(|key| key.to_string())
+                                .chain(self.gamepad.buttons.inputs(*control).iter().map(|button| button.to_string()))
+                                .collect();
+                            let keys = match inputs.as_slice() {
+                                _ if *waiting && index == *selected => "PRESS A KEY OR BUTTON".to_string(),
+                                [] => "-".to_string(),
+                                inputs => inputs.join(", "),
+                            };
+                            format!("{}  {}", control_label(*control), keys)
+                        })
+                        .chain(std::iter::once("BACK".to_string()))
+                        .collect();
+                    draw::draw_menu(&self.layout, "CONTROLS", &items, *selected, &mut canvas);
+                    let hint = match message {
+                        Some(message) => message.as_str(),
+                        None if *waiting => "Esc - cancel",
+                        None => "Enter - add key or button   Backspace - clear   Esc - back",
+                    };
+                    draw::draw_hint(&self.layout, hint, &mut canvas);
+                },
+                Scene::Playing(game) => draw::draw_game(&self.layout, &game.state, game.personal_best.as_ref(), &mut canvas),
+                Scene::Paused { game, selected } => {
+                    draw::draw_game(&self.layout, &game.state, game.personal_best.as_ref(), &mut canvas);
+                    draw::draw_shade(&self.layout, &mut canvas);
+                    let items = PAUSE_ITEMS.map(|item| item.to_string());
+                    draw::draw_menu(&self.layout, "PAUSED", &items, *selected, &mut canvas);
+                },
+                Scene::NameEntry { name, rank, .. } => draw::draw_name_entry(&self.layout, name, *rank, &mut canvas),
+                Scene::GameOver(game) | Scene::Results(game) => {
+                    draw::draw_game(&self.layout, &game.state, game.personal_best.as_ref(), &mut canvas);
+                    let hint = format!("{} - retry   L - leaderboard   Esc - menu", self.key_name(Control::Restart));
+                    draw::draw_hint(&self.layout, &hint, &mut canvas);
+                },
+                Scene::Leaderboard { mode, highlight, after_game } => {
+                    draw::draw_leaderboard(&self.layout, MODES[*mode], self.high_scores.table(MODES[*mode]), *highlight, &mut canvas);
+                    let hint = if *after_game {
+                        format!("Left / Right - mode   {} - retry   Esc - menu", self.key_name(Control::Restart))
+                    } else {
+                        "Left / Right - mode   Esc - back".to_string()
+                    };
+                    draw::draw_hint(&self.layout, &hint, &mut canvas);
+                },
+                Scene::Replay(playback) => draw::draw_playback(&self.layout, playback, &mut canvas),
+            }
+    
+            canvas.finish(ctx)?;
+            ggez::timer::yield_now();
+            Ok(())
+    }
+
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool) -> std::prelude::v1::Result<(), ggez::GameError> {
+        let Some(keycode) = input.keycode else { return Ok(()) };
+        self.press(ctx, Press::Key(keycode));
+
+        Ok(())
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, input: KeyInput) -> std::prelude::v1::Result<(), ggez::GameError> {
+        let Some(keycode) = input.keycode else { return Ok(()) };
+        self.release(Press::Key(keycode));
+
+        Ok(())
+    }
+
+    fn gamepad_button_down_event(&mut self, ctx: &mut Context, button: gilrs::Button, id: GamepadId) -> std::prelude::v1::Result<(), ggez::GameError> {
+        self.connect(ctx, id);
+        if let Some((button, true)) = self.gamepads.button(id, button, true) {
+            self.press(ctx, Press::Button(button));
+        }
+
+        Ok(())
+    }
+
+    fn gamepad_button_up_event(&mut self, _ctx: &mut Context, button: gilrs::Button, id: GamepadId) -> std::prelude::v1::Result<(), ggez::GameError> {
+        if let Some((button, false)) = self.gamepads.button(id, button, false) {
+            self.release(Press::Button(button));
+        }
+
+        Ok(())
+    }
+
+    fn gamepad_axis_event(&mut self, ctx: &mut Context, axis: gilrs::Axis, value: f32, id: GamepadId) -> std::prelude::v1::Result<(), ggez::GameError> {
+        self.connect(ctx, id);
+        for (button, pressed) in self.gamepads.axis(id, axis, value, self.gamepad.deadzone) {
+            if pressed {
+                self.press(ctx, Press::Button(button));
+            } else {
+                self.release(Press::Button(button));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) -> std::prelude::v1::Result<(), ggez::GameError> {
+        if let Scene::NameEntry { name, .. } = &mut self.scene {
+            if !character.is_control() && name.chars().count() < HighScores::MAX_NAME_LENGTH {
+                name.push(character);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> std::prelude::v1::Result<bool, ggez::GameError> {
+        if let Scene::Playing(game) | Scene::Paused { game, .. } = &self.scene {
+            self.save_replay(game);
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,79 @@
+use std::str::FromStr;
+
+use tetris_rs::GameSettings;
+
+/// Settings adjustable on the settings screen, in the order they are listed.
+pub const ITEMS: [&str; 13] = ["RANDOMIZER", "SCORING", "GRAVITY", "START LEVEL", "LOCK DELAY", "ROTATION", "180 KICKS", "DELAYS", "PREVIEW", "DAS", "ARR", "DCD", "SDF"];
+
+const RANDOMIZERS: [&str; 4] = ["7bag", "14bag", "tgm", "uniform"];
+const SCORINGS: [&str; 3] = ["guideline", "nes", "tgm"];
+const GRAVITY_CURVES: [&str; 3] = ["guideline", "nes", "tgm"];
+const LOCK_DELAY_MODES: [&str; 3] = ["extended", "infinite", "step"];
+const ROTATION_SYSTEMS: [&str; 4] = ["srs", "srs+", "ars", "nrs"];
+const HALF_KICK_TABLES: [&str; 2] = ["srs+", "none"];
+const DELAYS: [&str; 3] = ["none", "nes", "tgm"];
+const MAX_FRAMES: u32 = 60;
+const MAX_SDF: f32 = 40f32;
+
+/// Value of the setting at `index` of `ITEMS`, as shown on the settings screen.
+pub fn value(settings: &GameSettings, index: usize) -> String {
+    match index {
+        0 => name(&settings.randomizer, &RANDOMIZERS),
+        1 => name(&settings.scoring, &SCORINGS),
+        2 => name(&settings.gravity_curve, &GRAVITY_CURVES),
+        3 => settings.start_level.to_string(),
+        4 => name(&settings.lock_delay_mode, &LOCK_DELAY_MODES),
+        5 => name(&settings.rotation_system, &ROTATION_SYSTEMS),
+        6 => name(&settings.half_kick_table, &HALF_KICK_TABLES),
+        7 => name(&settings.delays, &DELAYS),
+        8 => settings.preview_length.to_string(),
+        9 => settings.handling.das.to_string(),
+        10 => settings.handling.arr.to_string(),
+        11 => settings.handling.dcd.to_string(),
+        _ if settings.handling.sdf.is_infinite() => "inf".to_string(),
+        _ => settings.handling.sdf.to_string(),
+    }
+}
+
+/// Moves the setting at `index` of `ITEMS` to its next (`direction` 1) or previous (`direction` -1) value.
+pub fn change(settings: &mut GameSettings, index: usize, direction: i32) {
+    match index {
+        0 => cycle(&mut settings.randomizer, &RANDOMIZERS, direction),
+        1 => cycle(&mut settings.scoring, &SCORINGS, direction),
+        2 => cycle(&mut settings.gravity_curve, &GRAVITY_CURVES, direction),
+        3 => settings.start_level = step(settings.start_level, direction, 1, GameSettings::MAX_START_LEVEL),
+        4 => cycle(&mut settings.lock_delay_mode, &LOCK_DELAY_MODES, direction),
+        5 => cycle(&mut settings.rotation_system, &ROTATION_SYSTEMS, direction),
+        6 => cycle(&mut settings.half_kick_table, &HALF_KICK_TABLES, direction),
+        7 => cycle(&mut settings.delays, &DELAYS, direction),
+        8 => settings.preview_length = step(settings.preview_length as u32, direction, 0, GameSettings::MAX_PREVIEW_LENGTH as u32) as usize,
+        9 => settings.handling.das = step(settings.handling.das, direction, 0, MAX_FRAMES),
+        10 => settings.handling.arr = step(settings.handling.arr, direction, 0, MAX_FRAMES),
+        11 => settings.handling.dcd = step(settings.handling.dcd, direction, 0, MAX_FRAMES),
+        // soft drop factors from 1 to `MAX_SDF`, followed by instant soft drop
+        _ => {
+            let sdf = if settings.handling.sdf.is_infinite() { MAX_SDF + 1f32 } else { settings.handling.sdf.round() };
+            let sdf = (sdf + direction as f32).clamp(1f32, MAX_SDF + 1f32);
+            settings.handling.sdf = if sdf > MAX_SDF { f32::INFINITY } else { sdf };
+        },
+    }
+}
+
+fn step(value: u32, direction: i32, min: u32, max: u32) -> u32 {
+    value.saturating_add_signed(direction).clamp(min, max)
+}
+
+/// Command line name of the value, settings loaded from elsewhere may match none of them.
+fn name<T: FromStr + PartialEq>(value: &T, names: &[&str]) -> String {
+    names.iter()
+        .find(|name| name.parse::<T>().is_ok_and(|named| named == *value))
+        .map_or("custom".to_string(), |name| name.to_string())
+}
+
+fn cycle<T: FromStr + PartialEq>(value: &mut T, names: &[&str], direction: i32) {
+    let count = names.len() as i32;
+    let index = names.iter().position(|name| name.parse::<T>().is_ok_and(|named| named == *value)).map_or(0, |index| index as i32);
+    if let Ok(next) = names[(index + direction).rem_euclid(count) as usize].parse() {
+        *value = next;
+    }
+}
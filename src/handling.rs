@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+/// Timings of horizontal auto-shift and soft drop, all measured in frames.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Handling {
+    /// Delayed auto shift - number of frames a direction has to be held before auto-repeat starts.
+    pub das: u32,
+    /// Auto repeat rate - number of frames between auto-repeated moves, 0 moves the tetromino instantly to the wall.
+    pub arr: u32,
+    /// DAS cut delay - number of frames the auto-repeat is suspended after a rotation or a new tetromino.
+    pub dcd: u32,
+    /// Soft drop factor - soft drop gravity as a multiple of the current level gravity,
+    /// `f32::INFINITY` drops the tetromino to the ground instantly.
+    pub sdf: f32,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Self {
+            das: 10,
+            arr: 2,
+            dcd: 0,
+            sdf: 20f32,
+        }
+    }
+}
+
+/// Tracks the horizontal auto-shift of the held direction.
+pub struct AutoShift {
+    direction: i32,
+    charge: u32,
+    repeat: u32,
+    cut: u32,
+}
+
+impl AutoShift {
+    pub fn new() -> Self {
+        Self {
+            direction: 0,
+            charge: 0,
+            repeat: 0,
+            cut: 0,
+        }
+    }
+
+    /// Advances one frame with `direction` (-1 left, 1 right, 0 none) held and returns by how many cells
+    /// the tetromino should move, `i32::MAX` (with the direction's sign) means up to the wall.
+    pub fn update(&mut self, direction: i32, handling: &Handling) -> i32 {
+        if direction != self.direction {
+            self.direction = direction;
+            self.charge = 0;
+            self.repeat = 0;
+            return direction;
+        }
+        if direction == 0 {
+            return 0;
+        }
+        self.charge = self.charge.saturating_add(1);
+        if self.cut > 0 {
+            self.cut -= 1;
+            return 0;
+        }
+        if self.charge < handling.das {
+            return 0;
+        }
+        if handling.arr == 0 {
+            return direction * i32::MAX;
+        }
+        if self.repeat > 0 {
+            self.repeat -= 1;
+            return 0;
+        }
+        self.repeat = handling.arr - 1;
+        direction
+    }
+
+    /// Suspends the auto-repeat of a held direction for `dcd` frames.
+    pub fn cut(&mut self, dcd: u32) {
+        if self.direction != 0 {
+            self.cut = dcd;
+        }
+    }
+}
+
+impl Default for AutoShift {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{action::Action, GameSettings, GameState};
+
+    /// Shifts returned while holding `direction` for `frames` frames.
+    fn hold(auto_shift: &mut AutoShift, handling: &Handling, direction: i32, frames: usize) -> Vec<i32> {
+        (0..frames).map(|_| auto_shift.update(direction, handling)).collect()
+    }
+
+    #[test]
+    fn das_charges_before_auto_repeat() {
+        let handling = Handling { das: 10, arr: 2, dcd: 0, ..Handling::default() };
+        let mut auto_shift = AutoShift::new();
+        assert_eq!(hold(&mut auto_shift, &handling, -1, 15), [-1, 0, 0, 0, 0, 0, 0, 0, 0, 0, -1, 0, -1, 0, -1]);
+        // releasing discharges
+        assert_eq!(hold(&mut auto_shift, &handling, 0, 1), [0]);
+        assert_eq!(hold(&mut auto_shift, &handling, 1, 3), [1, 0, 0]);
+    }
+
+    #[test]
+    fn zero_arr_shifts_to_the_wall() {
+        let handling = Handling { das: 3, arr: 0, dcd: 0, ..Handling::default() };
+        let mut auto_shift = AutoShift::new();
+        assert_eq!(hold(&mut auto_shift, &handling, 1, 5), [1, 0, 0, i32::MAX, i32::MAX]);
+    }
+
+    #[test]
+    fn dcd_suspends_auto_repeat() {
+        let handling = Handling { das: 10, arr: 2, dcd: 3, ..Handling::default() };
+        let mut auto_shift = AutoShift::new();
+        hold(&mut auto_shift, &handling, 1, 12);
+        auto_shift.cut(handling.dcd);
+        assert_eq!(hold(&mut auto_shift, &handling, 1, 6), [0, 0, 0, 1, 0, 1]);
+
+        // nothing to cut when no direction is held
+        let mut auto_shift = AutoShift::new();
+        auto_shift.cut(handling.dcd);
+        assert_eq!(hold(&mut auto_shift, &handling, 1, 1), [1]);
+    }
+
+    fn x(state: &GameState) -> i32 {
+        state.tetromino().unwrap().position.x
+    }
+
+    #[test]
+    fn last_pressed_direction_wins() {
+        let mut state = GameState::new(0, GameSettings::default());
+        let start = x(&state);
+        state.button(Action::MoveLeft).key_down();
+        state.update_game();
+        assert_eq!(x(&state), start - 1);
+        state.button(Action::MoveRight).key_down();
+        state.update_game();
+        assert_eq!(x(&state), start);
+        state.button(Action::MoveRight).key_up();
+        state.update_game();
+        assert_eq!(x(&state), start - 1);
+    }
+
+    #[test]
+    fn infinite_sdf_drops_to_the_ground_without_locking() {
+        let handling = Handling { sdf: f32::INFINITY, ..Handling::default() };
+        let mut state = GameState::new(0, GameSettings { handling, ..GameSettings::default() });
+        state.button(Action::SoftDrop).key_down();
+        state.update_game();
+        let ghost = state.ghost().unwrap().position;
+        assert_eq!(state.tetromino().unwrap().position, ghost);
+        assert_eq!(state.statistics().pieces(), 0);
+    }
+}
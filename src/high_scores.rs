@@ -0,0 +1,253 @@
+use std::{collections::BTreeMap, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{mode::{self, GameMode}, storage, GameState};
+
+const FILE_NAME: &str = "high_scores.toml";
+
+/// One entry of a leaderboard.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u64,
+    pub lines: u32,
+    pub level: u32,
+    /// Game time in frames.
+    pub frames: u64,
+    /// Day the game was played, `YYYY-MM-DD` (UTC).
+    pub date: String,
+    #[serde(with = "storage::seed_format")]
+    pub seed: u64,
+}
+
+impl HighScore {
+    /// Titles of the values returned by `columns`.
+    pub const COLUMN_TITLES: [&'static str; 6] = ["NAME", "SCORE", "LINES", "LV", "TIME", "DATE"];
+
+    pub fn new(state: &GameState, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            score: state.score().points(),
+            lines: state.score().lines(),
+            level: state.level(),
+            frames: state.frame(),
+            date: today(),
+            seed: state.seed(),
+        }
+    }
+
+    /// Values shown on a leaderboard.
+    pub fn columns(&self) -> [String; 6] {
+        [
+            self.name.clone(),
+            self.score.to_string(),
+            self.lines.to_string(),
+            self.level.to_string(),
+            mode::format_time(self.frames),
+            self.date.clone(),
+        ]
+    }
+
+    /// Sprints are ranked by time, other modes by score.
+    fn is_better_than(&self, other: &HighScore, mode: GameMode) -> bool {
+        match mode {
+            GameMode::Sprint { .. } => self.frames < other.frames,
+            GameMode::Endless | GameMode::Ultra => self.score > other.score,
+        }
+    }
+}
+
+/// Top `MAX_ENTRIES` results of every game mode, stored in the user's data directory.
+#[derive(Default, Serialize, Deserialize)]
+pub struct HighScores {
+    /// Leaderboards keyed by `GameMode::name`, best entry first.
+    #[serde(default)]
+    modes: BTreeMap<String, Vec<HighScore>>,
+}
+
+impl HighScores {
+    pub const MAX_ENTRIES: usize = 10;
+    pub const MAX_NAME_LENGTH: usize = 12;
+
+    /// Loads the high scores, a missing file means there are none yet.
+    pub fn load() -> Result<Self, String> {
+        storage::load_toml(&storage::data_path(FILE_NAME)?)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        storage::save_toml(&storage::data_path(FILE_NAME)?, self)
+    }
+
+    /// Leaderboard of the mode, best entry first.
+    pub fn table(&self, mode: GameMode) -> &[HighScore] {
+        self.modes.get(&mode.name()).map_or(&[], |entries| entries.as_slice())
+    }
+
+    /// Position (from 0) the ended game would take on its leaderboard, `None` when it does not qualify.
+    /// Unfinished Sprints, games without points and games not played by the default rules never qualify.
+    pub fn rank(&self, state: &GameState) -> Option<usize> {
+        let mode = state.settings().mode;
+        let completed = match mode {
+            GameMode::Sprint { .. } => state.is_finished(),
+            GameMode::Endless | GameMode::Ultra => state.has_ended() && state.score().points() > 0,
+        };
+        if !completed || !state.settings().is_ranked() {
+            return None;
+        }
+        self.rank_entry(mode, &HighScore::new(state, ""))
+    }
+
+    /// Adds the ended game to its leaderboard, returns its position or `None` when it does not qualify.
+    pub fn insert(&mut self, state: &GameState, name: &str) -> Option<usize> {
+        self.rank(state)?;
+        let name: String = name.trim().chars().take(Self::MAX_NAME_LENGTH).collect();
+        self.insert_entry(state.settings().mode, HighScore::new(state, &name))
+    }
+
+    fn rank_entry(&self, mode: GameMode, entry: &HighScore) -> Option<usize> {
+        // an equal result ranks below the one set earlier
+        let rank = self.table(mode).iter().take_while(|other| !entry.is_better_than(other, mode)).count();
+        Some(rank).filter(|&rank| rank < Self::MAX_ENTRIES)
+    }
+
+    fn insert_entry(&mut self, mode: GameMode, entry: HighScore) -> Option<usize> {
+        let rank = self.rank_entry(mode, &entry)?;
+        let entries = self.modes.entry(mode.name()).or_default();
+        entries.insert(rank, entry);
+        entries.truncate(Self::MAX_ENTRIES);
+        Some(rank)
+    }
+}
+
+/// Current UTC date as `YYYY-MM-DD`.
+fn today() -> String {
+    let seconds = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+    // civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+    use crate::{action::Action, handling::Handling, GameSettings};
+
+    const SPRINT: GameMode = GameMode::Sprint { lines: 40 };
+
+    fn entry(name: &str, score: u64, frames: u64) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            score,
+            lines: 10,
+            level: 2,
+            frames,
+            date: "2024-01-31".to_string(),
+            seed: u64::MAX,
+        }
+    }
+
+    fn names(high_scores: &HighScores, mode: GameMode) -> Vec<&str> {
+        high_scores.table(mode).iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tetris-rs-{}-{}.toml", name, std::process::id()))
+    }
+
+    /// An Endless game of hard drops only, played until it tops out.
+    fn topped_out(settings: GameSettings) -> GameState {
+        let mut state = GameState::new(0, settings);
+        while !state.has_ended() {
+            state.button(Action::HardDrop).key_down();
+            state.update_game();
+            state.button(Action::HardDrop).key_up();
+            state.update_game();
+        }
+        state
+    }
+
+    #[test]
+    fn entries_are_ranked_by_score_or_time() {
+        let mut high_scores = HighScores::default();
+        let entries = [("a", 500, 900), ("b", 300, 800), ("c", 800, 1000), ("d", 500, 700)];
+        for (name, score, frames) in entries {
+            high_scores.insert_entry(GameMode::Endless, entry(name, score, frames));
+            high_scores.insert_entry(SPRINT, entry(name, score, frames));
+        }
+        // an equal score ranks below the earlier one
+        assert_eq!(names(&high_scores, GameMode::Endless), ["c", "a", "d", "b"]);
+        assert_eq!(names(&high_scores, SPRINT), ["d", "b", "a", "c"]);
+        assert!(high_scores.table(GameMode::Ultra).is_empty());
+        assert!(high_scores.table(GameMode::Sprint { lines: 20 }).is_empty());
+    }
+
+    #[test]
+    fn tables_keep_the_best_ten() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=15 {
+            high_scores.insert_entry(GameMode::Endless, entry(&score.to_string(), score * 100, 0));
+        }
+        let scores: Vec<u64> = high_scores.table(GameMode::Endless).iter().map(|entry| entry.score).collect();
+        assert_eq!(scores, [1500, 1400, 1300, 1200, 1100, 1000, 900, 800, 700, 600]);
+        assert_eq!(high_scores.rank_entry(GameMode::Endless, &entry("", 600, 0)), None);
+        assert_eq!(high_scores.insert_entry(GameMode::Endless, entry("", 650, 0)), Some(9));
+        assert_eq!(high_scores.table(GameMode::Endless).len(), HighScores::MAX_ENTRIES);
+    }
+
+    #[test]
+    fn only_games_by_the_default_rules_qualify() {
+        let state = topped_out(GameSettings::default());
+        assert_eq!(HighScores::default().rank(&state), Some(0));
+        let handling = Handling { das: 6, arr: 0, ..Handling::default() };
+        assert_eq!(HighScores::default().rank(&topped_out(GameSettings { handling, ..GameSettings::default() })), Some(0));
+        assert_eq!(HighScores::default().rank(&topped_out(GameSettings { start_level: 2, ..GameSettings::default() })), None);
+
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.insert(&state, "  A very long player name  "), Some(0));
+        assert_eq!(names(&high_scores, GameMode::Endless), ["A very long "]);
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert_entry(GameMode::Endless, entry("a", 500, 900));
+        high_scores.insert_entry(GameMode::Endless, entry("b", 800, 700));
+        high_scores.insert_entry(SPRINT, entry("c", 0, 3000));
+        let path = temp_path("high-scores");
+        storage::save_toml(&path, &high_scores).unwrap();
+        let loaded: Result<HighScores, String> = storage::load_toml(&path);
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        for mode in [GameMode::Endless, SPRINT] {
+            assert_eq!(loaded.table(mode), high_scores.table(mode));
+        }
+    }
+
+    #[test]
+    fn missing_or_corrupt_file_starts_empty() {
+        let path = temp_path("missing-high-scores");
+        let loaded: HighScores = storage::load_toml(&path).unwrap();
+        assert!(loaded.modes.is_empty());
+
+        let path = temp_path("corrupt-high-scores");
+        let corrupt = path.with_extension("toml.corrupt");
+        fs::write(&path, "[modes]\nEndless = 12").unwrap();
+        assert!(storage::load_toml::<HighScores>(&path).is_err());
+        assert!(!path.exists());
+        assert!(corrupt.exists());
+        fs::remove_file(&corrupt).unwrap();
+        let loaded: HighScores = storage::load_toml(&path).unwrap();
+        assert!(loaded.modes.is_empty());
+    }
+}
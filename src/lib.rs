@@ -0,0 +1,34 @@
+//! Game logic of tetris-rs: board, tetrominoes, rotation, wall kicks, gravity and line clears.
+//!
+//! The engine does not depend on any graphics or windowing library, so it can be driven headless
+//! (bots, servers, tests) by calling [`GameState::update_game`] once per frame.
+
+pub mod action;
+pub mod board_tile;
+pub mod button_state;
+pub mod config;
+pub mod controls;
+pub mod delays;
+pub mod game_state;
+pub mod gravity;
+pub mod handling;
+pub mod high_scores;
+pub mod lock_delay;
+pub mod mode;
+pub mod options;
+pub mod personal_best;
+pub mod replay;
+pub mod rotation;
+pub mod rotation_system;
+pub mod scoring;
+pub mod settings;
+pub mod statistics;
+pub mod storage;
+pub mod tetromino;
+pub mod wall_kicks;
+
+pub use game_state::GameState;
+pub use settings::GameSettings;
+
+/// Frame rate all frame-based timings of the engine (gravity, delays, timers) are designed for.
+pub const FRAMES_PER_SECOND: u64 = 60;
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// What resets the lock delay of a tetromino lying on the ground.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum LockDelayMode {
+    /// Guideline "extended placement": moves and rotations reset the delay, but only a limited number of times.
+    /// The counter is cleared when the tetromino reaches a row lower than ever before.
+    ExtendedPlacement,
+    /// Every move and rotation resets the delay.
+    Infinite,
+    /// Only reaching a row lower than ever before resets the delay.
+    StepReset,
+}
+
+impl FromStr for LockDelayMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "extended" => Ok(LockDelayMode::ExtendedPlacement),
+            "infinite" => Ok(LockDelayMode::Infinite),
+            "step" => Ok(LockDelayMode::StepReset),
+            _ => Err(format!("unknown lock delay mode '{}', expected one of: extended, infinite, step", s))
+        }
+    }
+}
+
+/// Counts the frames a tetromino spends on the ground before it locks.
+pub struct LockDelay {
+    mode: LockDelayMode,
+    reset_limit: u32,
+    frames: u32,
+    resets: u32,
+    lowest_row: i32,
+}
+
+impl LockDelay {
+    pub fn new(mode: LockDelayMode, reset_limit: u32) -> Self {
+        Self {
+            mode,
+            reset_limit,
+            frames: 0,
+            resets: 0,
+            lowest_row: 0,
+        }
+    }
+
+    /// Starts counting for a newly spawned tetromino.
+    pub fn start(&mut self, row: i32) {
+        self.frames = 0;
+        self.resets = 0;
+        self.lowest_row = row;
+    }
+
+    /// Should be called after every successful move or rotation.
+    pub fn moved(&mut self) {
+        match self.mode {
+            LockDelayMode::ExtendedPlacement => {
+                if self.resets < self.reset_limit {
+                    self.frames = 0;
+                    self.resets += 1;
+                }
+            },
+            LockDelayMode::Infinite => self.frames = 0,
+            LockDelayMode::StepReset => {}
+        }
+    }
+
+    /// Should be called every time the tetromino moves down to `row`.
+    pub fn stepped_down(&mut self, row: i32) {
+        if row > self.lowest_row {
+            self.lowest_row = row;
+            self.resets = 0;
+            self.frames = 0;
+        } else if self.mode != LockDelayMode::StepReset {
+            self.frames = 0;
+        }
+    }
+
+    /// Counts one frame on the ground and returns whether the tetromino should lock.
+    pub fn grounded(&mut self, delay: u32) -> bool {
+        self.frames += 1;
+        if self.mode == LockDelayMode::ExtendedPlacement && self.resets >= self.reset_limit {
+            return true;
+        }
+        self.frames >= delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{action::Action, GameSettings, GameState};
+
+    const DELAY: u32 = 30;
+
+    /// Counts frames on the ground until the tetromino locks, gives up after `limit` frames.
+    fn frames_to_lock(lock_delay: &mut LockDelay, limit: u32) -> Option<u32> {
+        (1..=limit).find(|_| lock_delay.grounded(DELAY))
+    }
+
+    #[test]
+    fn locks_after_the_delay() {
+        for mode in [LockDelayMode::ExtendedPlacement, LockDelayMode::Infinite, LockDelayMode::StepReset] {
+            let mut lock_delay = LockDelay::new(mode, 15);
+            lock_delay.start(0);
+            assert_eq!(frames_to_lock(&mut lock_delay, 1000), Some(DELAY), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn extended_placement_locks_on_the_next_touchdown_after_the_last_reset() {
+        let mut lock_delay = LockDelay::new(LockDelayMode::ExtendedPlacement, 15);
+        lock_delay.start(5);
+        for _ in 0..15 {
+            assert!(!lock_delay.grounded(DELAY));
+            lock_delay.moved();
+        }
+        assert_eq!(frames_to_lock(&mut lock_delay, 1000), Some(1));
+
+        // a row lower than ever before gives all the resets back
+        lock_delay.stepped_down(6);
+        lock_delay.moved();
+        assert_eq!(frames_to_lock(&mut lock_delay, 1000), Some(DELAY));
+    }
+
+    #[test]
+    fn extended_placement_resets_up_to_the_limit() {
+        let mut lock_delay = LockDelay::new(LockDelayMode::ExtendedPlacement, 15);
+        lock_delay.start(5);
+        for _ in 0..14 {
+            assert!(!lock_delay.grounded(DELAY));
+            lock_delay.moved();
+        }
+        // the 15th reset still gives a full delay
+        assert_eq!(frames_to_lock(&mut lock_delay, DELAY - 1), None);
+        lock_delay.moved();
+        assert_eq!(frames_to_lock(&mut lock_delay, 1000), Some(1));
+    }
+
+    #[test]
+    fn step_reset_only_resets_on_a_new_lowest_row() {
+        let mut lock_delay = LockDelay::new(LockDelayMode::StepReset, 15);
+        lock_delay.start(5);
+        assert_eq!(frames_to_lock(&mut lock_delay, 10), None);
+        lock_delay.moved();
+        // kicked up and fallen back to the lowest row reached so far
+        lock_delay.stepped_down(5);
+        assert_eq!(frames_to_lock(&mut lock_delay, 1000), Some(DELAY - 10));
+
+        lock_delay.stepped_down(6);
+        assert_eq!(frames_to_lock(&mut lock_delay, 1000), Some(DELAY));
+    }
+
+    #[test]
+    fn infinite_never_locks_while_moving() {
+        let mut lock_delay = LockDelay::new(LockDelayMode::Infinite, 15);
+        lock_delay.start(5);
+        for _ in 0..10_000 {
+            assert!(!lock_delay.grounded(DELAY));
+            lock_delay.moved();
+        }
+    }
+
+    #[test]
+    fn hard_drop_locks_in_the_same_frame() {
+        for mode in [LockDelayMode::ExtendedPlacement, LockDelayMode::Infinite, LockDelayMode::StepReset] {
+            let mut state = GameState::new(0, GameSettings { lock_delay_mode: mode, ..GameSettings::default() });
+            state.button(Action::HardDrop).key_down();
+            state.update_game();
+            assert_eq!(state.last_lock().map(|lock| lock.frame), Some(0), "{:?}", mode);
+            assert_eq!(state.statistics().pieces(), 1, "{:?}", mode);
+        }
+    }
+}
@@ -1,96 +1,34 @@
-mod game_state;
-use game_state::GameState;
+mod gui;
 
-mod board_tile;
-mod tetromino;
-mod button_state;
-mod gravity;
-mod rotation;
-mod wall_kicks;
+use ggez::{event, GameResult};
+use tetris_rs::{options::Options, replay::{Playback, Replay}};
 
-use ggez::{
-    event, graphics,
-    input::keyboard::{KeyCode, KeyInput},
-    Context, GameResult,
-};
-
-
-const BOARD_WIDTH: usize = 10;
-const BOARD_HEIGHT: usize = 20;
-const NUMBER_OF_TILES: usize = BOARD_WIDTH * BOARD_HEIGHT;
+/// Size of one tile in logical pixels the screen is laid out with, the window scales it to the configured tile size.
 const TILE_SIZE: usize = 20;
 
 
-const SCREEN_SIZE: (f32, f32) = (
-    BOARD_WIDTH as f32 * TILE_SIZE as f32,
-    BOARD_HEIGHT as f32 * TILE_SIZE as f32,
-);
-
-const DESIRED_FPS: u32 = 60;
-
-
-impl event::EventHandler<ggez::GameError> for GameState {
-    fn update(&mut self, ctx: &mut Context) -> std::prelude::v1::Result<(), ggez::GameError> {
-        while ctx.time.check_update_time(DESIRED_FPS) {
-            if self.hold() {
-                continue;
-            }
-            self.update_game();
-        }
-
-        Ok(())
-    }
-
-    fn draw(&mut self, ctx: &mut Context) -> std::prelude::v1::Result<(), ggez::GameError> {
-            let mut canvas = graphics::Canvas::from_frame(ctx, graphics::Color::from([0.0, 0.0, 0.0, 1.0]));
-    
-            self.draw_game(&mut canvas);
-    
-            canvas.finish(ctx)?;
-            ggez::timer::yield_now();
-            Ok(())
-    }
-
-    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool) -> std::prelude::v1::Result<(), ggez::GameError> {
-        let keycode = input.keycode.unwrap();
-        match keycode {
-            KeyCode::Escape => ctx.request_quit(),
-            KeyCode::Up => self.rotate_clockwise_button_state.key_down(),
-            KeyCode::Numpad0 => self.rotate_counterclockwise_button_state.key_down(),
-            KeyCode::Down => self.soft_drop_button_state.key_down(),
-            KeyCode::Space => self.hard_drop_button_state.key_down(),
-            KeyCode::C => self.hold_button_state.key_down(),
-            KeyCode::Left => self.left_button_state.key_down(),
-            KeyCode::Right => self.right_button_state.key_down(),
-            _ => {}
-        }
-
-        Ok(())
-    }
-
-    fn key_up_event(&mut self, _ctx: &mut Context, input: KeyInput) -> std::prelude::v1::Result<(), ggez::GameError> {
-        let keycode = input.keycode.unwrap();
-        match keycode {
-            KeyCode::Down => self.soft_drop_button_state.key_up(),
-            KeyCode::Space => self.hard_drop_button_state.key_up(),
-            KeyCode::C => self.hold_button_state.key_up(),
-            KeyCode::Left => self.left_button_state.key_up(),
-            KeyCode::Right => self.right_button_state.key_up(),
-            KeyCode::Up => self.rotate_clockwise_button_state.key_up(),
-            KeyCode::Numpad0 => self.rotate_counterclockwise_button_state.key_up(),
-            _ => {}
-        }
-
-        Ok(())
-    }
-}
-
 fn main() -> GameResult {
+    let options = Options::parse(std::env::args().skip(1)).map_err(ggez::GameError::CustomError)?;
+    let replay = options.replay.as_deref().map(Replay::load).transpose().map_err(ggez::GameError::CustomError)?;
+
+    // a replay is shown on the board it was recorded on
+    let settings = replay.as_ref().map_or(options.settings, |replay| replay.settings);
+    let layout = gui::Layout {
+        board_width: settings.board_width,
+        board_height: settings.board_height,
+        side_panel_width: options.display.side_panel_width as usize,
+    };
+    let scale = options.display.tile_size as f32 / TILE_SIZE as f32;
+    let (width, height) = layout.screen_size();
+
     let (ctx, events_loop) = ggez::ContextBuilder::new("tetris", "MarcelSlom")
         .window_setup(ggez::conf::WindowSetup::default().title("Tetris!"))
-        .window_mode(ggez::conf::WindowMode::default().dimensions(SCREEN_SIZE.0, SCREEN_SIZE.1))
+        .window_mode(ggez::conf::WindowMode::default().dimensions(width * scale, height * scale))
         .build()?;
 
-    let state = GameState::new();
-    event::run(ctx, events_loop, state)
+    let app = match replay {
+        Some(replay) => gui::App::replay(layout, options, Playback::new(replay)),
+        None => gui::App::new(layout, options),
+    };
+    event::run(ctx, events_loop, app)
 }
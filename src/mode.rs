@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Goal of a game, checked after every lock.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum GameMode {
+    /// Played until the stack tops out.
+    #[default]
+    Endless,
+    /// Race to clear the given number of lines as fast as possible.
+    Sprint { lines: u32 },
+    /// Score as many points as possible within `ULTRA_FRAMES`.
+    Ultra,
+}
+
+impl GameMode {
+    /// Line targets a Sprint can be played with.
+    pub const SPRINT_LINES: [u32; 3] = [20, 40, 100];
+    /// Time limit of Ultra, 2 minutes.
+    pub const ULTRA_FRAMES: u64 = 120 * crate::FRAMES_PER_SECOND;
+
+    /// Whether the goal is reached with the given number of cleared lines.
+    pub fn is_complete(&self, lines: u32) -> bool {
+        match self {
+            GameMode::Endless | GameMode::Ultra => false,
+            GameMode::Sprint { lines: target } => lines >= *target,
+        }
+    }
+
+    /// Number of frames after which the game times out.
+    pub fn time_limit(&self) -> Option<u64> {
+        match self {
+            GameMode::Ultra => Some(Self::ULTRA_FRAMES),
+            GameMode::Endless | GameMode::Sprint { .. } => None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            GameMode::Endless => "Endless".to_string(),
+            GameMode::Sprint { lines } => format!("Sprint {}L", lines),
+            GameMode::Ultra => "Ultra".to_string(),
+        }
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "endless" => Ok(GameMode::Endless),
+            "sprint" => Ok(GameMode::Sprint { lines: 40 }),
+            "ultra" => Ok(GameMode::Ultra),
+            _ => Err(format!("unknown mode '{}', expected one of: endless, sprint, ultra", s))
+        }
+    }
+}
+
+/// Formats a number of frames as a `m:ss.cc` timer.
+pub fn format_time(frames: u64) -> String {
+    let centiseconds = frames * 100 / crate::FRAMES_PER_SECOND;
+    format!("{}:{:02}.{:02}", centiseconds / 6000, centiseconds / 100 % 60, centiseconds % 100)
+}
+
+/// Formats a difference of two timers as a signed number of seconds, e.g. `-1.25`.
+pub fn format_delta(frames: i64) -> String {
+    let centiseconds = frames.unsigned_abs() * 100 / crate::FRAMES_PER_SECOND;
+    let sign = if frames < 0 { '-' } else { '+' };
+    format!("{}{}.{:02}", sign, centiseconds / 100, centiseconds % 100)
+}
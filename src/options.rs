@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+use crate::{config::{Config, Display, Gamepad}, controls::KeyBindings, mode::GameMode, GameSettings};
+
+/// Command line options shared by the front ends, on top of the configuration file.
+pub struct Options {
+    pub seed: u64,
+    pub settings: GameSettings,
+    pub display: Display,
+    pub keys: KeyBindings,
+    pub gamepad: Gamepad,
+    /// Configuration file the options were read from, or would be if it existed; changed key bindings are saved to it.
+    pub config: Option<PathBuf>,
+    /// File the input of the game is recorded to.
+    pub record: Option<PathBuf>,
+    /// Replay file to play back instead of starting a new game.
+    pub replay: Option<PathBuf>,
+}
+
+impl Options {
+    /// Parses options (without the program name), unspecified ones keep their values from the configuration
+    /// file (the default one or the one given with `--config`) and the seed is random.
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let args: Vec<String> = args.collect();
+        let (config, config_path) = match args.iter().position(|arg| arg == "--config") {
+            Some(index) => {
+                let path = PathBuf::from(args.get(index + 1).ok_or("missing value for --config")?);
+                (Config::load(&path)?, Some(path))
+            },
+            None => (Config::load_default()?, Config::default_path().ok()),
+        };
+        let mut options = Options {
+            seed: rand::random(),
+            settings: config.game,
+            display: config.display,
+            keys: config.keys,
+            gamepad: config.gamepad,
+            config: config_path,
+            record: None,
+            replay: None,
+        };
+        let mut args = args.into_iter();
+        let mut sprint_lines = None;
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--mode" => options.settings.mode = value()?.parse()?,
+                "--lines" => sprint_lines = match value()?.parse() {
+                    Ok(lines) if GameMode::SPRINT_LINES.contains(&lines) => Some(lines),
+                    _ => return Err("--lines expects 20, 40 or 100".to_string()),
+                },
+                "--randomizer" => options.settings.randomizer = value()?.parse()?,
+                "--scoring" => options.settings.scoring = value()?.parse()?,
+                "--gravity" => options.settings.gravity_curve = value()?.parse()?,
+                "--level" => options.settings.start_level = match value()?.parse() {
+                    Ok(level) if (1..=GameSettings::MAX_START_LEVEL).contains(&level) => level,
+                    _ => return Err(format!("--level expects a number from 1 to {}", GameSettings::MAX_START_LEVEL)),
+                },
+                "--lock-delay" => options.settings.lock_delay_mode = value()?.parse()?,
+                "--rotation" => options.settings.rotation_system = value()?.parse()?,
+                "--180-kicks" => options.settings.half_kick_table = value()?.parse()?,
+                "--delays" => options.settings.delays = value()?.parse()?,
+                "--das" => options.settings.handling.das = parse_frames(&arg, value()?)?,
+                "--arr" => options.settings.handling.arr = parse_frames(&arg, value()?)?,
+                "--dcd" => options.settings.handling.dcd = parse_frames(&arg, value()?)?,
+                "--sdf" => options.settings.handling.sdf = match value()?.as_str() {
+                    "inf" => f32::INFINITY,
+                    sdf => match sdf.parse() {
+                        Ok(sdf) if sdf >= 1f32 => sdf,
+                        _ => return Err("--sdf expects a number not less than 1 or 'inf'".to_string()),
+                    },
+                },
+                "--preview" => options.settings.preview_length = match value()?.parse() {
+                    Ok(length) if length <= GameSettings::MAX_PREVIEW_LENGTH => length,
+                    _ => return Err(format!("--preview expects a number from 0 to {}", GameSettings::MAX_PREVIEW_LENGTH)),
+                },
+                "--width" => options.settings.board_width = match value()?.parse() {
+                    Ok(width) if (GameSettings::MIN_BOARD_WIDTH..=GameSettings::MAX_BOARD_WIDTH).contains(&width) => width,
+                    _ => return Err(format!("--width expects a number from {} to {}", GameSettings::MIN_BOARD_WIDTH, GameSettings::MAX_BOARD_WIDTH)),
+                },
+                "--height" => options.settings.board_height = match value()?.parse() {
+                    Ok(height) if (GameSettings::MIN_BOARD_HEIGHT..=GameSettings::MAX_BOARD_HEIGHT).contains(&height) => height,
+                    _ => return Err(format!("--height expects a number from {} to {}", GameSettings::MIN_BOARD_HEIGHT, GameSettings::MAX_BOARD_HEIGHT)),
+                },
+                "--tile-size" => options.display.tile_size = match value()?.parse() {
+                    Ok(size) if (Display::MIN_TILE_SIZE..=Display::MAX_TILE_SIZE).contains(&size) => size,
+                    _ => return Err(format!("--tile-size expects a number from {} to {}", Display::MIN_TILE_SIZE, Display::MAX_TILE_SIZE)),
+                },
+                "--fps" => options.display.fps = match value()?.parse() {
+                    Ok(fps) if (Display::MIN_FPS..=Display::MAX_FPS).contains(&fps) => fps,
+                    _ => return Err(format!("--fps expects a number from {} to {}", Display::MIN_FPS, Display::MAX_FPS)),
+                },
+                // already loaded above
+                "--config" => drop(value()?),
+                "--seed" => options.seed = value()?.parse().map_err(|_| "--seed expects an unsigned 64-bit number".to_string())?,
+                "--record" => options.record = Some(value()?.into()),
+                "--replay" => options.replay = Some(value()?.into()),
+                _ => return Err(format!("unknown option '{}'", arg)),
+            }
+        }
+        if let Some(lines) = sprint_lines {
+            match &mut options.settings.mode {
+                GameMode::Sprint { lines: target } => *target = lines,
+                _ => return Err("--lines can only be used with --mode sprint".to_string()),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse_frames(option: &str, value: String) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("{} expects a number of frames", option))
+}
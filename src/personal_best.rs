@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{mode::GameMode, storage, GameState};
+
+const FILE_NAME: &str = "personal_best.toml";
+
+/// Fastest finished Sprint for one line target.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SprintRecord {
+    /// Final time in frames.
+    pub frames: u64,
+    /// Frame each line was cleared at, see `GameState::splits`.
+    pub splits: Vec<u64>,
+}
+
+impl SprintRecord {
+    /// Difference between the time the last line of `splits` was cleared at and the time the record cleared the same line at.
+    pub fn delta(&self, splits: &[u64]) -> Option<i64> {
+        let index = splits.len().checked_sub(1)?;
+        let record = self.splits.get(index)?;
+        Some(splits[index] as i64 - *record as i64)
+    }
+}
+
+/// Personal bests stored in the user's data directory.
+#[derive(Default, Serialize, Deserialize)]
+pub struct PersonalBests {
+    /// Sprint records keyed by the line target (TOML keys have to be strings).
+    #[serde(default)]
+    sprint: BTreeMap<String, SprintRecord>,
+}
+
+impl PersonalBests {
+    /// Loads the personal bests, a missing file means there are none yet.
+    pub fn load() -> Result<Self, String> {
+        storage::load_toml(&storage::data_path(FILE_NAME)?)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        storage::save_toml(&storage::data_path(FILE_NAME)?, self)
+    }
+
+    pub fn sprint(&self, lines: u32) -> Option<&SprintRecord> {
+        self.sprint.get(&lines.to_string())
+    }
+
+    /// Records the result of a finished game, returns whether it is a new personal best. Only games played by the
+    /// default rules (see `GameSettings::is_ranked`) count.
+    pub fn submit(&mut self, state: &GameState) -> bool {
+        let lines = match state.settings().mode {
+            GameMode::Sprint { lines } if state.is_finished() && state.settings().is_ranked() => lines,
+            _ => return false,
+        };
+        if self.sprint(lines).is_some_and(|record| record.frames <= state.frame()) {
+            return false;
+        }
+        self.sprint.insert(lines.to_string(), SprintRecord {
+            frames: state.frame(),
+            splits: state.splits().to_vec(),
+        });
+        true
+    }
+}
@@ -0,0 +1,357 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{action::Action, storage, GameSettings, GameState};
+
+/// Press or release of an action's button before the given frame was simulated.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InputEvent {
+    pub frame: u64,
+    pub action: Action,
+    pub pressed: bool,
+}
+
+/// Everything needed to re-simulate a game: its seed, settings and the input of every frame.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    #[serde(with = "storage::seed_format")]
+    pub seed: u64,
+    /// Number of simulated frames.
+    pub frames: u64,
+    #[serde(with = "events_format")]
+    pub events: Vec<InputEvent>,
+    pub settings: GameSettings,
+}
+
+impl Replay {
+    pub fn new(seed: u64, settings: GameSettings) -> Self {
+        Self {
+            seed,
+            frames: 0,
+            events: Vec::new(),
+            settings,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        storage::save_toml(path, self)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("can not read replay {}: {}", path.display(), e))?;
+        let replay: Replay = toml::from_str(&content).map_err(|e| format!("invalid replay {}: {}", path.display(), e))?;
+        replay.settings.validate().map_err(|e| format!("invalid replay {}: {}", path.display(), e))?;
+        Ok(replay)
+    }
+}
+
+/// Forwards input to a game and records it into a replay.
+pub struct Recorder {
+    replay: Replay,
+}
+
+impl Recorder {
+    pub fn new(state: &GameState) -> Self {
+        Self {
+            replay: Replay::new(state.seed(), *state.settings()),
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn key_down(&mut self, state: &mut GameState, action: Action) {
+        let button = state.button(action);
+        if !button.is_pressed() {
+            button.key_down();
+            self.record(action, true);
+        }
+    }
+
+    pub fn key_up(&mut self, state: &mut GameState, action: Action) {
+        let button = state.button(action);
+        if button.is_pressed() {
+            button.key_up();
+            self.record(action, false);
+        }
+    }
+
+    /// Advances the game by one frame.
+    pub fn update(&mut self, state: &mut GameState) {
+        state.update_game();
+        self.replay.frames += 1;
+    }
+
+    fn record(&mut self, action: Action, pressed: bool) {
+        self.replay.events.push(InputEvent {
+            frame: self.replay.frames,
+            action,
+            pressed,
+        });
+    }
+}
+
+/// Re-simulates a replay, with pause, frame stepping, speed control and seeking.
+pub struct Playback {
+    replay: Replay,
+    state: GameState,
+    frame: u64,
+    next_event: usize,
+    paused: bool,
+    speed_index: usize,
+    progress: f32,
+}
+
+impl Playback {
+    pub const SPEEDS: [f32; 5] = [0.25f32, 0.5f32, 1f32, 2f32, 4f32];
+
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            state: GameState::new(replay.seed, replay.settings),
+            replay,
+            frame: 0,
+            next_event: 0,
+            paused: false,
+            speed_index: 2,
+            progress: 0f32,
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn length(&self) -> u64 {
+        self.replay.frames
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.replay.frames
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn speed(&self) -> f32 {
+        Self::SPEEDS[self.speed_index]
+    }
+
+    pub fn faster(&mut self) {
+        self.speed_index = (self.speed_index + 1).min(Self::SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed_index = self.speed_index.saturating_sub(1);
+    }
+
+    /// Should be called `FRAMES_PER_SECOND` times per second, simulates as many frames as the current speed requires.
+    pub fn update(&mut self) {
+        if self.paused {
+            return;
+        }
+        self.progress += self.speed();
+        while self.progress >= 1f32 {
+            self.progress -= 1f32;
+            self.step();
+        }
+    }
+
+    /// Simulates a single frame, does nothing at the end of the replay.
+    pub fn step(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+        while let Some(event) = self.replay.events.get(self.next_event).filter(|event| event.frame <= self.frame) {
+            let button = self.state.button(event.action);
+            if event.pressed {
+                button.key_down();
+            } else {
+                button.key_up();
+            }
+            self.next_event += 1;
+        }
+        self.state.update_game();
+        self.frame += 1;
+    }
+
+    /// Moves to the given frame; seeking backwards re-simulates the game from the start.
+    pub fn seek(&mut self, frame: u64) {
+        if frame < self.frame {
+            self.state = GameState::new(self.replay.seed, self.replay.settings);
+            self.frame = 0;
+            self.next_event = 0;
+        }
+        while self.frame < frame && !self.is_finished() {
+            self.step();
+        }
+    }
+}
+
+/// Events are stored compactly as space separated `<frames since previous event><action code><+ or ->` tokens.
+mod events_format {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::InputEvent;
+    use crate::action::Action;
+
+    pub fn serialize<S: Serializer>(events: &[InputEvent], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut previous = 0;
+        let tokens: Vec<String> = events.iter()
+            .map(|event| {
+                let token = format!("{}{}{}", event.frame - previous, event.action.code(), if event.pressed { '+' } else { '-' });
+                previous = event.frame;
+                token
+            })
+            .collect();
+        serializer.serialize_str(&tokens.join(" "))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<InputEvent>, D::Error> {
+        let content = String::deserialize(deserializer)?;
+        let mut frame = 0;
+        content.split_whitespace()
+            .map(|token| {
+                let invalid = || serde::de::Error::custom(format!("invalid replay event '{}'", token));
+                let mut chars = token.chars().rev();
+                let pressed = match chars.next() {
+                    Some('+') => true,
+                    Some('-') => false,
+                    _ => return Err(invalid()),
+                };
+                let action = chars.next().and_then(Action::from_code).ok_or_else(invalid)?;
+                let delta: u64 = token[..token.len() - 2].parse().map_err(|_| invalid())?;
+                frame += delta;
+                Ok(InputEvent { frame, action, pressed })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{delays::Delays, tetromino::TetrominoKind};
+
+    const SEED: u64 = 7;
+
+    /// What a replay has to reproduce: the board, the score and the frame.
+    fn snapshot(state: &GameState) -> (Vec<Option<TetrominoKind>>, u64, u32, u64) {
+        let board = state.board().iter().map(|tile| tile.kind).collect();
+        (board, state.score().points(), state.score().lines(), state.frame())
+    }
+
+    fn to_toml(replay: &Replay) -> String {
+        toml::Value::try_from(replay).and_then(|value| toml::to_string(&value)).unwrap()
+    }
+
+    /// Plays a game with a fixed input pattern: every 40 frames the tetromino is shifted by a different amount,
+    /// rotated and hard dropped.
+    fn record(settings: GameSettings, frames: u64) -> (Replay, GameState) {
+        // shift direction and the number of frames it is held, long holds auto-shift
+        const SHIFTS: [(Action, u64); 6] = [
+            (Action::MoveLeft, 1), (Action::MoveRight, 13), (Action::MoveLeft, 13),
+            (Action::MoveRight, 1), (Action::MoveLeft, 30), (Action::MoveRight, 30),
+        ];
+        const ROTATIONS: [Action; 3] = [Action::RotateClockwise, Action::RotateCounterClockwise, Action::Rotate180];
+        let mut state = GameState::new(SEED, settings);
+        let mut recorder = Recorder::new(&state);
+        for frame in 0..frames {
+            let piece = (frame / 40) as usize;
+            let (shift, held) = SHIFTS[piece % SHIFTS.len()];
+            let rotation = ROTATIONS[piece % ROTATIONS.len()];
+            match frame % 40 {
+                0 => recorder.key_down(&mut state, shift),
+                2 => recorder.key_down(&mut state, rotation),
+                3 => recorder.key_up(&mut state, rotation),
+                35 => recorder.key_down(&mut state, Action::HardDrop),
+                36 => recorder.key_up(&mut state, Action::HardDrop),
+                _ => {}
+            }
+            if frame % 40 == held {
+                recorder.key_up(&mut state, shift);
+            }
+            recorder.update(&mut state);
+        }
+        (recorder.replay().clone(), state)
+    }
+
+    #[test]
+    fn events_round_trip() {
+        let mut replay = Replay::new(SEED, GameSettings::default());
+        replay.frames = 100;
+        replay.events = vec![
+            InputEvent { frame: 0, action: Action::MoveLeft, pressed: true },
+            InputEvent { frame: 0, action: Action::Hold, pressed: true },
+            InputEvent { frame: 12, action: Action::MoveLeft, pressed: false },
+            InputEvent { frame: 12, action: Action::Hold, pressed: false },
+            InputEvent { frame: 99, action: Action::Pause, pressed: true },
+        ];
+        let content = to_toml(&replay);
+        assert!(content.contains("events = \"0L+ 0C+ 12L- 0C- 87P+\""), "{}", content);
+        let parsed: Replay = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.events, replay.events);
+        assert_eq!((parsed.seed, parsed.frames), (replay.seed, replay.frames));
+
+        let (replay, _) = record(GameSettings::default(), 600);
+        let parsed: Replay = toml::from_str(&to_toml(&replay)).unwrap();
+        assert_eq!(parsed.events, replay.events);
+    }
+
+    #[test]
+    fn invalid_events_are_rejected() {
+        let replay = to_toml(&Replay::new(SEED, GameSettings::default()));
+        for events in ["0L", "0L*", "0Q+", "L+", "-1L+"] {
+            let content = replay.replace("events = \"\"", &format!("events = \"{}\"", events));
+            assert!(toml::from_str::<Replay>(&content).is_err(), "{}", events);
+        }
+    }
+
+    #[test]
+    fn playback_reproduces_the_recorded_game() {
+        for delays in [Delays::NONE, Delays::TGM] {
+            let (replay, state) = record(GameSettings { delays, ..GameSettings::default() }, 1000);
+            assert!(state.statistics().pieces() > 10);
+            let path = std::env::temp_dir().join(format!("tetris-rs-replay-{}.toml", std::process::id()));
+            replay.save(&path).unwrap();
+            let loaded = Replay::load(&path);
+            fs::remove_file(&path).unwrap();
+            let mut playback = Playback::new(loaded.unwrap());
+            while !playback.is_finished() {
+                playback.step();
+            }
+            assert_eq!(playback.frame(), 1000);
+            assert_eq!(snapshot(playback.state()), snapshot(&state));
+        }
+    }
+
+    #[test]
+    fn seeking_matches_playback() {
+        let (replay, _) = record(GameSettings::default(), 1200);
+        let mut straight = Playback::new(replay.clone());
+        for _ in 0..500 {
+            straight.step();
+        }
+
+        let mut seeking = Playback::new(replay);
+        seeking.seek(900);
+        assert_eq!(seeking.frame(), 900);
+        seeking.seek(500);
+        assert_eq!(seeking.frame(), 500);
+        assert_eq!(snapshot(seeking.state()), snapshot(straight.state()));
+
+        seeking.seek(100_000);
+        assert!(seeking.is_finished());
+        assert_eq!(seeking.frame(), 1200);
+    }
+}
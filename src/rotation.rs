@@ -1,4 +1,4 @@
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Rotation {
     _0 = 0,
     R = 1,
@@ -12,6 +12,7 @@ impl Rotation {
         match direction {
             RotationDirection::Clockwise => Self::next_clockwise(*self),
             RotationDirection::CounterClockwise => Self::next_counter_clockwise(*self),
+            RotationDirection::Half => Self::next_clockwise(Self::next_clockwise(*self)),
         }
     }
 
@@ -35,7 +36,9 @@ impl Rotation {
     
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum RotationDirection {
-    Clockwise, CounterClockwise
+    Clockwise, CounterClockwise,
+    /// 180° rotation.
+    Half
 }
\ No newline at end of file
@@ -0,0 +1,316 @@
+use std::str::FromStr;
+
+use rusttype::Point;
+use serde::{Deserialize, Serialize};
+
+use crate::{rotation::{Rotation, RotationDirection}, tetromino::{Tetromino, TetrominoKind}, wall_kicks::{self, HalfKickTable}};
+
+/// Shapes and wall kicks of the tetrominoes.
+///
+/// Rotation states are named as in SRS whatever the system: in state 0 the flat side of T, J and L is at the bottom,
+/// R is a clockwise turn from there. The T-spin detection relies on that to tell where the front of the T is, the
+/// position of the T in its box may differ between systems (see `t_center`).
+pub trait RotationSystem {
+    /// Rotation state tetrominoes of the kind spawn in.
+    fn spawn_rotation(&self, kind: TetrominoKind) -> Rotation;
+
+    /// Tiles of the rotation state as (x, y) in the bounding box of the kind, y growing downward.
+    fn tiles(&self, kind: TetrominoKind, rotation: Rotation) -> [(usize, usize); 4];
+
+    /// Center tile of the T in the rotation state, the T-spin corners are its diagonal neighbors.
+    fn t_center(&self, rotation: Rotation) -> (usize, usize) {
+        let tiles = self.tiles(TetrominoKind::T, rotation);
+        // the only tile touching the three others
+        let neighbors = |(x, y): (usize, usize)| tiles.iter().filter(|other| x.abs_diff(other.0) + y.abs_diff(other.1) == 1).count();
+        tiles.into_iter().find(|&tile| neighbors(tile) == 3).expect("a T has a center")
+    }
+
+    /// Offsets to try in order, in board coordinates, when turning the tetromino in `direction`; `occupied` tells
+    /// whether a board tile is filled or outside the board.
+    fn kicks(&self, tetromino: &Tetromino, direction: RotationDirection, occupied: &dyn Fn(Point<i32>) -> bool) -> Vec<Point<i32>>;
+}
+
+/// The guideline Super Rotation System, or with `plus` TETR.IO's SRS+ whose I kicks are symmetric.
+pub struct Srs {
+    plus: bool,
+    half_kick_table: HalfKickTable,
+}
+
+impl Srs {
+    pub fn new(plus: bool, half_kick_table: HalfKickTable) -> Self {
+        Self { plus, half_kick_table }
+    }
+
+    /// Row of a 90° kick table.
+    fn wall_kick_index(start: Rotation, direction: RotationDirection) -> usize {
+        if direction == RotationDirection::Clockwise {
+            2 * start as usize
+        } else {
+            let finish = start.next(RotationDirection::CounterClockwise);
+            2 * finish as usize + 1
+        }
+    }
+}
+
+impl RotationSystem for Srs {
+    fn spawn_rotation(&self, _kind: TetrominoKind) -> Rotation {
+        Rotation::_0
+    }
+
+    fn tiles(&self, kind: TetrominoKind, rotation: Rotation) -> [(usize, usize); 4] {
+        let states = match kind {
+            TetrominoKind::I => [
+                [(0, 1), (1, 1), (2, 1), (3, 1)],
+                [(2, 0), (2, 1), (2, 2), (2, 3)],
+                [(0, 2), (1, 2), (2, 2), (3, 2)],
+                [(1, 0), (1, 1), (1, 2), (1, 3)],
+            ],
+            TetrominoKind::O => [[(0, 0), (1, 0), (0, 1), (1, 1)]; 4],
+            TetrominoKind::T => [
+                [(1, 0), (0, 1), (1, 1), (2, 1)],
+                [(1, 0), (1, 1), (2, 1), (1, 2)],
+                [(0, 1), (1, 1), (2, 1), (1, 2)],
+                [(1, 0), (0, 1), (1, 1), (1, 2)],
+            ],
+            TetrominoKind::S => [
+                [(1, 0), (2, 0), (0, 1), (1, 1)],
+                [(1, 0), (1, 1), (2, 1), (2, 2)],
+                [(1, 1), (2, 1), (0, 2), (1, 2)],
+                [(0, 0), (0, 1), (1, 1), (1, 2)],
+            ],
+            TetrominoKind::Z => [
+                [(0, 0), (1, 0), (1, 1), (2, 1)],
+                [(2, 0), (1, 1), (2, 1), (1, 2)],
+                [(0, 1), (1, 1), (1, 2), (2, 2)],
+                [(1, 0), (0, 1), (1, 1), (0, 2)],
+            ],
+            TetrominoKind::J => [
+                [(0, 0), (0, 1), (1, 1), (2, 1)],
+                [(1, 0), (2, 0), (1, 1), (1, 2)],
+                [(0, 1), (1, 1), (2, 1), (2, 2)],
+                [(1, 0), (1, 1), (0, 2), (1, 2)],
+            ],
+            TetrominoKind::L => [
+                [(2, 0), (0, 1), (1, 1), (2, 1)],
+                [(1, 0), (1, 1), (1, 2), (2, 2)],
+                [(0, 1), (1, 1), (2, 1), (0, 2)],
+                [(0, 0), (1, 0), (1, 1), (1, 2)],
+            ],
+        };
+        states[rotation as usize]
+    }
+
+    fn kicks(&self, tetromino: &Tetromino, direction: RotationDirection, _occupied: &dyn Fn(Point<i32>) -> bool) -> Vec<Point<i32>> {
+        let start = tetromino.current_rotation;
+        let kicks: &[Point<i32>] = match tetromino.kind {
+            TetrominoKind::O => &wall_kicks::NO_WALL_KICKS,
+            _ if direction == RotationDirection::Half => self.half_kick_table.kicks(start),
+            TetrominoKind::I if self.plus => &wall_kicks::SRS_PLUS_I_WALL_KICKS[Self::wall_kick_index(start, direction)],
+            TetrominoKind::I => &wall_kicks::I_WALL_KICKS[Self::wall_kick_index(start, direction)],
+            _ => &wall_kicks::WALL_KICKS[Self::wall_kick_index(start, direction)],
+        };
+        // the tables have y growing upward, the board downward
+        kicks.iter().map(|kick| Point {x: kick.x, y: -kick.y}).collect()
+    }
+}
+
+/// The Arika Rotation System of TGM: tetrominoes spawn pointing down, stay at the bottom of their box when lying flat
+/// and kick one tile right, then left, except for I and except when the center column rule applies.
+pub struct Ars;
+
+impl Ars {
+    /// Center column rule of J, L and T: when the first tile blocking the rotation, going through the turned
+    /// tetromino row by row from the top left, is in the middle column of its box, the tetromino does not kick.
+    fn center_column_blocks(tetromino: &Tetromino, rotation: Rotation, occupied: &dyn Fn(Point<i32>) -> bool) -> bool {
+        let mut tiles = Ars.tiles(tetromino.kind, rotation);
+        tiles.sort_by_key(|&(x, y)| (y, x));
+        tiles.iter()
+            .find(|&&(x, y)| occupied(Point {x: tetromino.position.x + x as i32, y: tetromino.position.y + y as i32}))
+            .is_some_and(|&(x, _)| x == 1)
+    }
+}
+
+impl RotationSystem for Ars {
+    fn spawn_rotation(&self, _kind: TetrominoKind) -> Rotation {
+        Rotation::_2
+    }
+
+    fn tiles(&self, kind: TetrominoKind, rotation: Rotation) -> [(usize, usize); 4] {
+        let states = match kind {
+            TetrominoKind::I => [
+                [(0, 1), (1, 1), (2, 1), (3, 1)],
+                [(2, 0), (2, 1), (2, 2), (2, 3)],
+                [(0, 1), (1, 1), (2, 1), (3, 1)],
+                [(2, 0), (2, 1), (2, 2), (2, 3)],
+            ],
+            TetrominoKind::O => [[(0, 0), (1, 0), (0, 1), (1, 1)]; 4],
+            TetrominoKind::T => [
+                [(1, 1), (0, 2), (1, 2), (2, 2)],
+                [(1, 0), (1, 1), (2, 1), (1, 2)],
+                [(0, 1), (1, 1), (2, 1), (1, 2)],
+                [(1, 0), (0, 1), (1, 1), (1, 2)],
+            ],
+            TetrominoKind::S => [
+                [(1, 1), (2, 1), (0, 2), (1, 2)],
+                [(0, 0), (0, 1), (1, 1), (1, 2)],
+                [(1, 1), (2, 1), (0, 2), (1, 2)],
+                [(0, 0), (0, 1), (1, 1), (1, 2)],
+            ],
+            TetrominoKind::Z => [
+                [(0, 1), (1, 1), (1, 2), (2, 2)],
+                [(2, 0), (1, 1), (2, 1), (1, 2)],
+                [(0, 1), (1, 1), (1, 2), (2, 2)],
+                [(2, 0), (1, 1), (2, 1), (1, 2)],
+            ],
+            TetrominoKind::J => [
+                [(0, 1), (0, 2), (1, 2), (2, 2)],
+                [(1, 0), (2, 0), (1, 1), (1, 2)],
+                [(0, 1), (1, 1), (2, 1), (2, 2)],
+                [(1, 0), (1, 1), (0, 2), (1, 2)],
+            ],
+            TetrominoKind::L => [
+                [(2, 1), (0, 2), (1, 2), (2, 2)],
+                [(1, 0), (1, 1), (1, 2), (2, 2)],
+                [(0, 1), (1, 1), (2, 1), (0, 2)],
+                [(0, 0), (1, 0), (1, 1), (1, 2)],
+            ],
+        };
+        states[rotation as usize]
+    }
+
+    fn kicks(&self, tetromino: &Tetromino, direction: RotationDirection, occupied: &dyn Fn(Point<i32>) -> bool) -> Vec<Point<i32>> {
+        let rotation = tetromino.current_rotation.next(direction);
+        match tetromino.kind {
+            TetrominoKind::I | TetrominoKind::O => wall_kicks::NO_WALL_KICKS.to_vec(),
+            TetrominoKind::J | TetrominoKind::L | TetrominoKind::T if Self::center_column_blocks(tetromino, rotation, occupied) => {
+                wall_kicks::NO_WALL_KICKS.to_vec()
+            },
+            _ => wall_kicks::ARS_WALL_KICKS.to_vec(),
+        }
+    }
+}
+
+/// The Nintendo Rotation System of the NES: tetrominoes spawn pointing down, turn around a fixed tile without ever
+/// kicking, and I, S and Z only have two states.
+pub struct Nrs;
+
+impl RotationSystem for Nrs {
+    fn spawn_rotation(&self, _kind: TetrominoKind) -> Rotation {
+        Rotation::_2
+    }
+
+    fn tiles(&self, kind: TetrominoKind, rotation: Rotation) -> [(usize, usize); 4] {
+        let horizontal = matches!(rotation, Rotation::_0 | Rotation::_2);
+        match kind {
+            TetrominoKind::I if horizontal => [(0, 2), (1, 2), (2, 2), (3, 2)],
+            TetrominoKind::I => [(2, 0), (2, 1), (2, 2), (2, 3)],
+            TetrominoKind::S if horizontal => [(1, 1), (2, 1), (0, 2), (1, 2)],
+            TetrominoKind::S => [(1, 0), (1, 1), (2, 1), (2, 2)],
+            TetrominoKind::Z if horizontal => [(0, 1), (1, 1), (1, 2), (2, 2)],
+            TetrominoKind::Z => [(2, 0), (1, 1), (2, 1), (1, 2)],
+            // the other kinds turn around the center of their box just like in SRS
+            _ => Srs::new(false, HalfKickTable::None).tiles(kind, rotation),
+        }
+    }
+
+    fn kicks(&self, _tetromino: &Tetromino, _direction: RotationDirection, _occupied: &dyn Fn(Point<i32>) -> bool) -> Vec<Point<i32>> {
+        wall_kicks::NO_WALL_KICKS.to_vec()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RotationSystemKind {
+    Srs,
+    SrsPlus,
+    Ars,
+    Nrs,
+}
+
+impl RotationSystemKind {
+    /// Creates the rotation system; `half_kick_table` is only used by SRS and SRS+, the others turn 180° with their
+    /// usual kicks.
+    pub fn create(&self, half_kick_table: HalfKickTable) -> Box<dyn RotationSystem> {
+        match self {
+            RotationSystemKind::Srs => Box::new(Srs::new(false, half_kick_table)),
+            RotationSystemKind::SrsPlus => Box::new(Srs::new(true, half_kick_table)),
+            RotationSystemKind::Ars => Box::new(Ars),
+            RotationSystemKind::Nrs => Box::new(Nrs),
+        }
+    }
+}
+
+impl FromStr for RotationSystemKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "srs" => Ok(RotationSystemKind::Srs),
+            "srs+" => Ok(RotationSystemKind::SrsPlus),
+            "ars" => Ok(RotationSystemKind::Ars),
+            "nrs" => Ok(RotationSystemKind::Nrs),
+            _ => Err(format!("unknown rotation system '{}', expected one of: srs, srs+, ars, nrs", s))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(system: &dyn RotationSystem, kind: TetrominoKind, directions: &[RotationDirection]) -> Vec<&'static str> {
+        let mut tetromino = Tetromino::new(kind, system);
+        for direction in directions {
+            tetromino.rotate(*direction, system);
+        }
+        tetromino.shape.iter()
+            .map(|row| match row.as_slice() {
+                [false, true, false] => ".#.",
+                [true, true, false] => "##.",
+                [false, true, true] => ".##",
+                [true, true, true] => "###",
+                [false, false, false] => "...",
+                [true, false, false] => "#..",
+                [false, false, true] => "..#",
+                _ => "?",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn srs_turns_around_the_center() {
+        let srs = Srs::new(false, HalfKickTable::SrsPlus);
+        assert_eq!(shape(&srs, TetrominoKind::T, &[]), [".#.", "###", "..."]);
+        assert_eq!(shape(&srs, TetrominoKind::T, &[RotationDirection::CounterClockwise]), [".#.", "##.", ".#."]);
+        assert_eq!(shape(&srs, TetrominoKind::T, &[RotationDirection::Half]), ["...", "###", ".#."]);
+        assert_eq!(
+            shape(&srs, TetrominoKind::T, &[RotationDirection::CounterClockwise, RotationDirection::Half]),
+            shape(&srs, TetrominoKind::T, &[RotationDirection::Clockwise]),
+        );
+    }
+
+    #[test]
+    fn ars_keeps_flat_tetrominoes_at_the_bottom() {
+        assert_eq!(shape(&Ars, TetrominoKind::T, &[]), ["...", "###", ".#."]);
+        assert_eq!(shape(&Ars, TetrominoKind::T, &[RotationDirection::Half]), ["...", ".#.", "###"]);
+        assert_eq!(shape(&Ars, TetrominoKind::L, &[RotationDirection::Clockwise]), ["##.", ".#.", ".#."]);
+        assert_eq!(shape(&Ars, TetrominoKind::S, &[RotationDirection::Half]), ["...", ".##", "##."]);
+    }
+
+    #[test]
+    fn nrs_has_two_states_for_s() {
+        assert_eq!(shape(&Nrs, TetrominoKind::S, &[]), ["...", ".##", "##."]);
+        assert_eq!(shape(&Nrs, TetrominoKind::S, &[RotationDirection::Clockwise]), [".#.", ".##", "..#"]);
+        assert_eq!(shape(&Nrs, TetrominoKind::S, &[RotationDirection::CounterClockwise]), [".#.", ".##", "..#"]);
+        assert_eq!(shape(&Nrs, TetrominoKind::J, &[]), ["...", "###", "..#"]);
+    }
+
+    #[test]
+    fn t_center_follows_the_t() {
+        let srs = Srs::new(false, HalfKickTable::SrsPlus);
+        assert_eq!(srs.t_center(Rotation::_0), (1, 1));
+        assert_eq!(Nrs.t_center(Rotation::_0), (1, 1));
+        // the ARS T sits at the bottom of its box, so its center moves between states
+        assert_eq!(Ars.t_center(Rotation::_0), (1, 2));
+        assert_eq!(Ars.t_center(Ars.spawn_rotation(TetrominoKind::T)), (1, 1));
+    }
+}
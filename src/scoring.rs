@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TSpin {
+    None,
+    Mini,
+    Full,
+}
+
+/// Result of locking a tetromino, as seen by the scoring system.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LineClear {
+    pub lines: u32,
+    pub t_spin: TSpin,
+    pub perfect_clear: bool,
+}
+
+impl LineClear {
+    /// Tetrises and line clearing T-spins are "difficult" clears, consecutive difficult clears are back-to-back.
+    pub fn is_difficult(&self) -> bool {
+        self.lines == 4 || (self.lines > 0 && self.t_spin != TSpin::None)
+    }
+
+    /// Name of the clear shown to the player, e.g. "T-SPIN DOUBLE"; `None` when no lines were cleared without a T-spin.
+    pub fn name(&self) -> Option<String> {
+        let lines = ["", "SINGLE", "DOUBLE", "TRIPLE", "TETRIS"][self.lines.min(4) as usize];
+        let name = match self.t_spin {
+            TSpin::None if lines.is_empty() => return None,
+            TSpin::None => lines.to_string(),
+            TSpin::Mini => format!("T-SPIN MINI {}", lines),
+            TSpin::Full => format!("T-SPIN {}", lines),
+        };
+        Some(name.trim_end().to_string())
+    }
+}
+
+/// Points awarded by a scoring system. Indexes of the per-line arrays are numbers of cleared lines.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ScoringTable {
+    pub line_clear: [u32; 5],
+    pub t_spin: [u32; 4],
+    pub t_spin_mini: [u32; 3],
+    /// Bonus added on top of the line clear when the board ends up empty.
+    pub perfect_clear: [u32; 5],
+    /// Applied to the line clear (or T-spin) points of a back-to-back difficult clear.
+    pub back_to_back_percent: u32,
+    /// Multiplied by the combo counter (number of consecutive line clearing locks minus one).
+    pub combo: u32,
+    pub soft_drop_per_cell: u32,
+    pub hard_drop_per_cell: u32,
+    /// Whether line clear, T-spin, perfect clear and combo points are multiplied by the level.
+    pub level_multiplier: bool,
+}
+
+impl ScoringTable {
+    pub const GUIDELINE: ScoringTable = ScoringTable {
+        line_clear: [0, 100, 300, 500, 800],
+        t_spin: [400, 800, 1200, 1600],
+        t_spin_mini: [100, 200, 400],
+        perfect_clear: [0, 800, 1200, 1800, 2000],
+        back_to_back_percent: 150,
+        combo: 50,
+        soft_drop_per_cell: 1,
+        hard_drop_per_cell: 2,
+        level_multiplier: true,
+    };
+
+    /// NES scoring, 40/100/300/1200 points times (NES level + 1), which is the 1-based level used here.
+    /// No T-spin, combo nor back-to-back bonuses.
+    pub const NES: ScoringTable = ScoringTable {
+        line_clear: [0, 40, 100, 300, 1200],
+        t_spin: [0, 40, 100, 300],
+        t_spin_mini: [0, 40, 100],
+        perfect_clear: [0; 5],
+        back_to_back_percent: 100,
+        combo: 0,
+        soft_drop_per_cell: 1,
+        hard_drop_per_cell: 0,
+        level_multiplier: true,
+    };
+
+    /// Table form approximation of TGM scoring: no T-spin bonus, large combo and bravo (perfect clear) bonuses.
+    pub const TGM: ScoringTable = ScoringTable {
+        line_clear: [0, 10, 30, 60, 100],
+        t_spin: [0, 10, 30, 60],
+        t_spin_mini: [0, 10, 30],
+        perfect_clear: [0, 30, 90, 180, 300],
+        back_to_back_percent: 100,
+        combo: 20,
+        soft_drop_per_cell: 1,
+        hard_drop_per_cell: 1,
+        level_multiplier: true,
+    };
+}
+
+impl Default for ScoringTable {
+    fn default() -> Self {
+        ScoringTable::GUIDELINE
+    }
+}
+
+impl FromStr for ScoringTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "guideline" => Ok(ScoringTable::GUIDELINE),
+            "nes" => Ok(ScoringTable::NES),
+            "tgm" => Ok(ScoringTable::TGM),
+            _ => Err(format!("unknown scoring '{}', expected one of: guideline, nes, tgm", s))
+        }
+    }
+}
+
+/// Running score of a game, keeps track of back-to-back chains and combos.
+pub struct Score {
+    table: ScoringTable,
+    points: u64,
+    lines: u32,
+    combo: Option<u32>,
+    back_to_back: bool,
+}
+
+impl Score {
+    pub fn new(table: ScoringTable) -> Self {
+        Self {
+            table,
+            points: 0,
+            lines: 0,
+            combo: None,
+            back_to_back: false,
+        }
+    }
+
+    pub fn points(&self) -> u64 {
+        self.points
+    }
+
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+
+    /// Number of consecutive line clearing locks minus one, `None` when the last lock cleared nothing.
+    pub fn combo(&self) -> Option<u32> {
+        self.combo
+    }
+
+    /// Whether the last line clear was a difficult one, so the next difficult clear is back-to-back.
+    pub fn is_back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    /// Scores a lock of a tetromino and returns the awarded points.
+    pub fn line_clear(&mut self, clear: LineClear, level: u32) -> u32 {
+        let lines = clear.lines as usize;
+        let mut points = match clear.t_spin {
+            TSpin::None => self.table.line_clear[lines],
+            TSpin::Mini => self.table.t_spin_mini[lines.min(2)],
+            TSpin::Full => self.table.t_spin[lines.min(3)],
+        };
+
+        if clear.lines > 0 {
+            if clear.is_difficult() {
+                if self.back_to_back {
+                    points = points * self.table.back_to_back_percent / 100;
+                }
+                self.back_to_back = true;
+            } else {
+                self.back_to_back = false;
+            }
+            self.combo = Some(self.combo.map_or(0, |combo| combo + 1));
+            points += self.table.combo * self.combo.unwrap();
+        } else {
+            self.combo = None;
+        }
+
+        if clear.perfect_clear {
+            points += self.table.perfect_clear[lines];
+        }
+        if self.table.level_multiplier {
+            points *= level.max(1);
+        }
+
+        self.lines += clear.lines;
+        self.points += points as u64;
+        points
+    }
+
+    pub fn soft_drop(&mut self, cells: u32) {
+        self.points += (cells * self.table.soft_drop_per_cell) as u64;
+    }
+
+    pub fn hard_drop(&mut self, cells: u32) {
+        self.points += (cells * self.table.hard_drop_per_cell) as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: LineClear = clear(0);
+    const SINGLE: LineClear = clear(1);
+    const DOUBLE: LineClear = clear(2);
+    const TETRIS: LineClear = clear(4);
+    const T_SPIN_ZERO: LineClear = LineClear { lines: 0, t_spin: TSpin::Full, perfect_clear: false };
+    const T_SPIN_DOUBLE: LineClear = LineClear { lines: 2, t_spin: TSpin::Full, perfect_clear: false };
+    const T_SPIN_MINI_SINGLE: LineClear = LineClear { lines: 1, t_spin: TSpin::Mini, perfect_clear: false };
+
+    /// Name, level, and the locks with the points each of them is worth.
+    type Case<'a> = (&'a str, u32, &'a [(LineClear, u32)]);
+
+    const fn clear(lines: u32) -> LineClear {
+        LineClear { lines, t_spin: TSpin::None, perfect_clear: false }
+    }
+
+    const fn perfect(lines: u32) -> LineClear {
+        LineClear { lines, t_spin: TSpin::None, perfect_clear: true }
+    }
+
+    /// Scores the locks one by one and checks the points awarded for each of them.
+    fn check(name: &str, table: ScoringTable, level: u32, locks: &[(LineClear, u32)]) {
+        let mut score = Score::new(table);
+        let mut total = 0u64;
+        for (i, &(clear, expected)) in locks.iter().enumerate() {
+            assert_eq!(score.line_clear(clear, level), expected, "{}, lock {}", name, i);
+            total += expected as u64;
+        }
+        assert_eq!(score.points(), total, "{}", name);
+    }
+
+    #[test]
+    fn guideline_line_clears() {
+        // locks clearing nothing in between keep the combo at bay without breaking back-to-back
+        let cases: &[Case] = &[
+            ("plain clears", 1, &[
+                (SINGLE, 100), (EMPTY, 0), (DOUBLE, 300), (EMPTY, 0), (clear(3), 500), (EMPTY, 0), (TETRIS, 800),
+            ]),
+            ("tetris after tetris", 1, &[(TETRIS, 800), (EMPTY, 0), (TETRIS, 1200), (EMPTY, 0), (TETRIS, 1200)]),
+            ("tetris after t-spin", 1, &[(T_SPIN_DOUBLE, 1200), (EMPTY, 0), (TETRIS, 1200)]),
+            ("t-spin after tetris", 1, &[
+                (TETRIS, 800), (EMPTY, 0), (T_SPIN_DOUBLE, 1800), (EMPTY, 0), (T_SPIN_MINI_SINGLE, 300),
+            ]),
+            ("single breaks back-to-back", 1, &[(TETRIS, 800), (EMPTY, 0), (SINGLE, 100), (EMPTY, 0), (TETRIS, 800)]),
+            ("t-spin without lines keeps back-to-back", 1, &[(TETRIS, 800), (T_SPIN_ZERO, 400), (EMPTY, 0), (TETRIS, 1200)]),
+            ("combo grows and resets", 1, &[
+                (SINGLE, 100), (SINGLE, 150), (DOUBLE, 400), (SINGLE, 250), (EMPTY, 0), (SINGLE, 100),
+            ]),
+            ("perfect clears", 1, &[(perfect(1), 900), (EMPTY, 0), (perfect(2), 1500), (EMPTY, 0), (perfect(4), 2800)]),
+            ("perfect clear bonus skips back-to-back", 1, &[(TETRIS, 800), (EMPTY, 0), (perfect(4), 3200)]),
+            ("level multiplier", 5, &[(SINGLE, 500), (TETRIS, 4250), (EMPTY, 0), (T_SPIN_DOUBLE, 9000)]),
+        ];
+        for &(name, level, locks) in cases {
+            check(name, ScoringTable::GUIDELINE, level, locks);
+        }
+    }
+
+    #[test]
+    fn nes_line_clears() {
+        let cases: &[Case] = &[
+            ("plain clears", 1, &[(SINGLE, 40), (DOUBLE, 100), (clear(3), 300), (TETRIS, 1200)]),
+            ("no back-to-back", 1, &[(TETRIS, 1200), (EMPTY, 0), (TETRIS, 1200)]),
+            ("t-spins score as plain clears", 1, &[(T_SPIN_DOUBLE, 100), (T_SPIN_MINI_SINGLE, 40)]),
+            ("no perfect clear bonus", 1, &[(perfect(4), 1200)]),
+            ("level multiplier", 10, &[(SINGLE, 400), (TETRIS, 12000)]),
+        ];
+        for &(name, level, locks) in cases {
+            check(name, ScoringTable::NES, level, locks);
+        }
+    }
+
+    #[test]
+    fn tgm_line_clears() {
+        let cases: &[Case] = &[
+            ("plain clears", 1, &[
+                (SINGLE, 10), (EMPTY, 0), (DOUBLE, 30), (EMPTY, 0), (clear(3), 60), (EMPTY, 0), (TETRIS, 100),
+            ]),
+            ("no back-to-back", 1, &[(TETRIS, 100), (EMPTY, 0), (TETRIS, 100)]),
+            ("combo", 1, &[(SINGLE, 10), (DOUBLE, 50), (TETRIS, 140), (EMPTY, 0), (SINGLE, 10)]),
+            ("bravo", 1, &[(perfect(1), 40), (EMPTY, 0), (perfect(4), 400)]),
+            ("level multiplier", 3, &[(SINGLE, 30), (SINGLE, 90)]),
+        ];
+        for &(name, level, locks) in cases {
+            check(name, ScoringTable::TGM, level, locks);
+        }
+    }
+
+    #[test]
+    fn drops() {
+        let cases = [
+            (ScoringTable::GUIDELINE, 10, 40),
+            (ScoringTable::NES, 10, 0),
+            (ScoringTable::TGM, 10, 20),
+        ];
+        for (table, soft, hard) in cases {
+            let mut score = Score::new(table);
+            score.soft_drop(10);
+            assert_eq!(score.points(), soft);
+            score.hard_drop(20);
+            assert_eq!(score.points(), soft + hard);
+        }
+    }
+
+    #[test]
+    fn drops_are_not_multiplied_by_level() {
+        let mut score = Score::new(ScoringTable::GUIDELINE);
+        score.line_clear(SINGLE, 10);
+        score.hard_drop(5);
+        score.soft_drop(5);
+        assert_eq!(score.points(), 1000 + 10 + 5);
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use crate::{delays::Delays, gravity::GravityCurve, handling::Handling, lock_delay::LockDelayMode, mode::GameMode, scoring::ScoringTable, rotation_system::RotationSystemKind, tetromino::RandomizerKind, wall_kicks::HalfKickTable};
+
+/// Rules a game is started with.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameSettings {
+    pub mode: GameMode,
+    /// Board width in tiles, from `MIN_BOARD_WIDTH` to `MAX_BOARD_WIDTH`.
+    pub board_width: usize,
+    /// Board height in tiles, from `MIN_BOARD_HEIGHT` to `MAX_BOARD_HEIGHT`.
+    pub board_height: usize,
+    /// Hidden rows above the visible field, from `MIN_BUFFER_HEIGHT` to `MAX_BUFFER_HEIGHT`; tetrominoes spawn in the
+    /// lowest two of them.
+    pub buffer_height: usize,
+    pub randomizer: RandomizerKind,
+    /// Number of upcoming tetrominoes shown in the next queue, at most `MAX_PREVIEW_LENGTH`.
+    pub preview_length: usize,
+    pub scoring: ScoringTable,
+    pub gravity_curve: GravityCurve,
+    /// Level the game starts at, from 1 to `MAX_START_LEVEL`.
+    pub start_level: u32,
+    /// Number of cleared lines needed to advance to the next level.
+    pub lines_per_level: u32,
+    pub lock_delay_mode: LockDelayMode,
+    /// Number of moves and rotations that can reset the lock delay in `LockDelayMode::ExtendedPlacement`.
+    pub move_reset_limit: u32,
+    /// Shapes and wall kicks of the tetrominoes.
+    pub rotation_system: RotationSystemKind,
+    /// Wall kicks of 180° rotations with SRS and SRS+.
+    pub half_kick_table: HalfKickTable,
+    /// Whether a tetromino locking partly above the visible field ends the game, not only one locking entirely above it.
+    pub partial_lock_out: bool,
+    pub handling: Handling,
+    pub delays: Delays,
+}
+
+impl GameSettings {
+    pub const MAX_PREVIEW_LENGTH: usize = 7;
+    /// Every tetromino fits horizontally in any orientation.
+    pub const MIN_BOARD_WIDTH: usize = 4;
+    pub const MAX_BOARD_WIDTH: usize = 40;
+    /// The side panels of the front ends need that many rows.
+    pub const MIN_BOARD_HEIGHT: usize = 16;
+    pub const MAX_BOARD_HEIGHT: usize = 50;
+    /// Tetrominoes need two rows to spawn in.
+    pub const MIN_BUFFER_HEIGHT: usize = 2;
+    pub const MAX_BUFFER_HEIGHT: usize = 40;
+    /// Every gravity curve reaches its top speed well before that.
+    pub const MAX_START_LEVEL: u32 = 30;
+
+    /// Checks the values that can not be enforced by their types, the error names the offending field.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(Self::MIN_BOARD_WIDTH..=Self::MAX_BOARD_WIDTH).contains(&self.board_width) {
+            return Err(format!("board_width must be from {} to {}, got {}", Self::MIN_BOARD_WIDTH, Self::MAX_BOARD_WIDTH, self.board_width));
+        }
+        if !(Self::MIN_BOARD_HEIGHT..=Self::MAX_BOARD_HEIGHT).contains(&self.board_height) {
+            return Err(format!("board_height must be from {} to {}, got {}", Self::MIN_BOARD_HEIGHT, Self::MAX_BOARD_HEIGHT, self.board_height));
+        }
+        if !(Self::MIN_BUFFER_HEIGHT..=Self::MAX_BUFFER_HEIGHT).contains(&self.buffer_height) {
+            return Err(format!("buffer_height must be from {} to {}, got {}", Self::MIN_BUFFER_HEIGHT, Self::MAX_BUFFER_HEIGHT, self.buffer_height));
+        }
+        if self.preview_length > Self::MAX_PREVIEW_LENGTH {
+            return Err(format!("preview_length must be from 0 to {}, got {}", Self::MAX_PREVIEW_LENGTH, self.preview_length));
+        }
+        if !(1..=Self::MAX_START_LEVEL).contains(&self.start_level) {
+            return Err(format!("start_level must be from 1 to {}, got {}", Self::MAX_START_LEVEL, self.start_level));
+        }
+        if self.lines_per_level < 1 {
+            return Err("lines_per_level must be greater than 0".to_string());
+        }
+        if let GameMode::Sprint { lines: 0 } = self.mode {
+            return Err("mode.lines must be greater than 0".to_string());
+        }
+        if self.handling.sdf.is_nan() || self.handling.sdf < 1f32 {
+            return Err(format!("handling.sdf must be at least 1 or inf, got {}", self.handling.sdf));
+        }
+        Ok(())
+    }
+
+    /// Whether the game is played by the default rules, apart from its mode and the player's handling. Only such
+    /// games go on the leaderboards and set personal bests, so their results are comparable.
+    pub fn is_ranked(&self) -> bool {
+        *self == GameSettings { mode: self.mode, handling: self.handling, ..GameSettings::default() }
+    }
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Endless,
+            board_width: 10,
+            board_height: 20,
+            buffer_height: 20,
+            randomizer: RandomizerKind::SevenBag,
+            preview_length: 5,
+            scoring: ScoringTable::GUIDELINE,
+            gravity_curve: GravityCurve::Guideline,
+            start_level: 1,
+            lines_per_level: 10,
+            lock_delay_mode: LockDelayMode::ExtendedPlacement,
+            move_reset_limit: 15,
+            rotation_system: RotationSystemKind::Srs,
+            half_kick_table: HalfKickTable::SrsPlus,
+            partial_lock_out: false,
+            handling: Handling::default(),
+            delays: Delays::default(),
+        }
+    }
+}
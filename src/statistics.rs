@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use crate::scoring::{LineClear, TSpin};
+
+/// Counters of a game shown on the results screen.
+#[derive(Clone, Default, Debug)]
+pub struct Statistics {
+    pieces: u32,
+    /// Number of clears keyed by the T-spin kind and the number of cleared lines.
+    clears: HashMap<(TSpin, u32), u32>,
+    perfect_clears: u32,
+}
+
+impl Statistics {
+    /// Order the clears are listed in by `breakdown`.
+    const CLEAR_ORDER: [(TSpin, u32); 11] = [
+        (TSpin::None, 1),
+        (TSpin::None, 2),
+        (TSpin::None, 3),
+        (TSpin::None, 4),
+        (TSpin::Mini, 0),
+        (TSpin::Mini, 1),
+        (TSpin::Mini, 2),
+        (TSpin::Full, 0),
+        (TSpin::Full, 1),
+        (TSpin::Full, 2),
+        (TSpin::Full, 3),
+    ];
+
+    /// Counts a locked tetromino and the clear it made.
+    pub fn record(&mut self, clear: LineClear) {
+        self.pieces += 1;
+        if clear.name().is_some() {
+            *self.clears.entry((clear.t_spin, clear.lines)).or_insert(0) += 1;
+        }
+        if clear.perfect_clear {
+            self.perfect_clears += 1;
+        }
+    }
+
+    /// Number of locked tetrominoes.
+    pub fn pieces(&self) -> u32 {
+        self.pieces
+    }
+
+    /// Pieces locked per second over the given number of frames.
+    pub fn pieces_per_second(&self, frames: u64) -> f32 {
+        if frames == 0 {
+            return 0f32;
+        }
+        self.pieces as f32 * crate::FRAMES_PER_SECOND as f32 / frames as f32
+    }
+
+    /// Names and counts of the clears that happened at least once, from singles to T-spin triples, then perfect clears.
+    pub fn breakdown(&self) -> Vec<(String, u32)> {
+        let mut breakdown: Vec<(String, u32)> = Self::CLEAR_ORDER.iter()
+            .filter_map(|&(t_spin, lines)| {
+                let count = *self.clears.get(&(t_spin, lines))?;
+                let name = LineClear { lines, t_spin, perfect_clear: false }.name()?;
+                Some((name, count))
+            })
+            .collect();
+        if self.perfect_clears > 0 {
+            breakdown.push(("PERFECT CLEAR".to_string(), self.perfect_clears));
+        }
+        breakdown
+    }
+}
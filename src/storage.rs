@@ -0,0 +1,60 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Path of a file in the user's data directory, e.g. `~/.local/share/tetris-rs` on Linux.
+pub fn data_path(file_name: &str) -> Result<PathBuf, String> {
+    directories::ProjectDirs::from("", "MarcelSlom", "tetris-rs")
+        .map(|dirs| dirs.data_dir().join(file_name))
+        .ok_or_else(|| "can not determine the user's data directory".to_string())
+}
+
+/// Path of a file in the user's configuration directory, e.g. `~/.config/tetris-rs` on Linux.
+pub fn config_path(file_name: &str) -> Result<PathBuf, String> {
+    directories::ProjectDirs::from("", "MarcelSlom", "tetris-rs")
+        .map(|dirs| dirs.config_dir().join(file_name))
+        .ok_or_else(|| "can not determine the user's configuration directory".to_string())
+}
+
+/// Reads a TOML file, a missing file yields the default value. A file that can not be parsed is renamed
+/// (with a `.corrupt` suffix), so it is kept for inspection instead of being overwritten by the next save.
+pub fn load_toml<T: DeserializeOwned + Default>(path: &Path) -> Result<T, String> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("can not read {}: {}", path.display(), e))?;
+    toml::from_str(&content).map_err(|e| {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(".corrupt");
+        let backup = PathBuf::from(backup);
+        match fs::rename(path, &backup) {
+            Ok(()) => format!("invalid {}: {}, it was moved to {}", path.display(), e, backup.display()),
+            Err(_) => format!("invalid {}: {}", path.display(), e),
+        }
+    })
+}
+
+/// Writes a TOML file, creating its directory when needed.
+pub fn save_toml<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    // going through `Value` emits nested tables after plain values, as TOML requires
+    let content = toml::Value::try_from(value)
+        .and_then(|value| toml::to_string(&value))
+        .map_err(|e| format!("can not serialize {}: {}", path.display(), e))?;
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|e| format!("can not create {}: {}", directory.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("can not write {}: {}", path.display(), e))
+}
+
+/// Stores a `u64` (e.g. a seed) as a string, TOML integers can not hold every u64.
+pub(crate) mod seed_format {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&seed.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
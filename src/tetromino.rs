@@ -1,43 +1,33 @@
 use rusttype::Point;
-use ggez::graphics::Color;
-use rand::{distributions::{Distribution, Standard}, Rng};
+use std::str::FromStr;
+use rand::{distributions::{Distribution, Standard}, seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{board_tile::BoardTile, rotation::{Rotation, RotationDirection}};
+use crate::{board_tile::BoardTile, rotation::{Rotation, RotationDirection}, rotation_system::RotationSystem};
 
 #[derive(Clone)]
 pub struct Tetromino {
     pub kind: TetrominoKind,
-    pub color: Color,
     pub position: Point<i32>,
     pub shape: Vec<Vec<bool>>,
     pub current_rotation: Rotation, 
 }
 
 impl Tetromino {
-    pub fn new(kind: TetrominoKind) -> Self {
+    /// Creates the tetromino in the spawn state of the rotation system.
+    pub fn new(kind: TetrominoKind, system: &dyn RotationSystem) -> Self {
+        let rotation = system.spawn_rotation(kind);
         Tetromino {
             kind,
-            color: kind.color(),
             position: Point {x: 0, y: 0},
-            shape: kind.shape(),
-            current_rotation: Rotation::_0,
-        }
-    }
-
-    pub fn random() -> Self {
-        let random_kind: TetrominoKind = rand::random();
-        Tetromino {
-            kind: random_kind,
-            color: random_kind.color(),
-            position: Point {x: 0, y: 0},
-            shape: random_kind.shape(),
-            current_rotation: Rotation::_0,
+            shape: kind.shape(system, rotation),
+            current_rotation: rotation,
         }
     }
 
     pub fn to_ghost(&self) -> Self {
-        let mut ghost = self.clone();
-        ghost.color = Color::from_rgb(100, 100, 100);
+        let ghost = self.clone();
        // let last_row_index = ghost.shape.iter().enumerate().rev().find(|x| x.1.iter().any(|xx| *xx)).map(|x| x.0).unwrap();
        // ghost.position.y = (crate::BOARD_HEIGHT - ghost.shape.len() + last_row_index) as i32;
         ghost
@@ -46,56 +36,25 @@ impl Tetromino {
     pub fn tiles(&self) -> Vec<BoardTile> {
         let capacity = self.shape.iter().map(|x| x.iter().filter(|&&xx| xx).count()).sum::<usize>();
         let mut tiles = Vec::<BoardTile>::with_capacity(capacity);
-        let mut y = self.position.y;
-        for row in &self.shape {
-            let mut x = self.position.x;
-            for item in row {
+        for (y, row) in (self.position.y..).zip(self.shape.iter()) {
+            for (x, item) in (self.position.x..).zip(row.iter()) {
                 if *item {
-                    tiles.push(BoardTile::new(x as u32, y as u32, self.color))
+                    tiles.push(BoardTile::new(x as u32, y as u32, self.kind))
                 }
-                x += 1;
             }
-            y += 1;
         }
         tiles
     }
 
-     pub fn rotate(&mut self, direction: RotationDirection) {
-        match direction{
-            RotationDirection::Clockwise => {
-                let new_width = self.shape.len();
-                let new_height = self.shape[0].len();
-                let mut rotated: Vec<Vec<bool>> = Vec::with_capacity(new_height);
-                for i in 0..new_height {
-                    let mut new_row: Vec<bool> = Vec::with_capacity(new_width);
-                    for old_row in self.shape.iter().rev() {
-                        new_row.push(old_row[i]);
-                    }
-                    rotated.push(new_row);
-                }
-                self.shape = rotated;
-                self.current_rotation = self.current_rotation.next(RotationDirection::Clockwise);
-            },
-            RotationDirection::CounterClockwise => {
-                let new_width = self.shape.len();
-                let new_height = self.shape[0].len();
-                let mut rotated: Vec<Vec<bool>> = Vec::with_capacity(new_height);
-                for i in 0..new_height {
-                    let mut new_row: Vec<bool> = Vec::with_capacity(new_width);
-                    for old_row in &self.shape {
-                        new_row.push(old_row[new_height - i - 1]);
-                    }
-                    rotated.push(new_row);
-                }
-                self.shape = rotated;
-                self.current_rotation = self.current_rotation.next(RotationDirection::CounterClockwise);
-            }
-        }
+    /// Turns the tetromino within its bounding box, which stays in place; wall kicks are up to the caller.
+    pub fn rotate(&mut self, direction: RotationDirection, system: &dyn RotationSystem) {
+        self.current_rotation = self.current_rotation.next(direction);
+        self.shape = self.kind.shape(system, self.current_rotation);
     }
 }
 
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum TetrominoKind {
     I,
     O,
@@ -107,28 +66,32 @@ pub enum TetrominoKind {
 }
 
 impl TetrominoKind {
-    fn color(&self) -> Color {
+    pub const ALL: [TetrominoKind; 7] = [
+        TetrominoKind::I,
+        TetrominoKind::O,
+        TetrominoKind::T,
+        TetrominoKind::S,
+        TetrominoKind::Z,
+        TetrominoKind::J,
+        TetrominoKind::L,
+    ];
+
+    /// Size of the square bounding box the tetromino rotates in.
+    fn box_size(&self) -> usize {
         match self {
-            TetrominoKind::I => Color::CYAN,
-            TetrominoKind::O => Color::YELLOW,
-            TetrominoKind::T => Color::from_rgb(0xa0, 0x20, 0xf0), //Purple,
-            TetrominoKind::S => Color::GREEN,
-            TetrominoKind::Z => Color::RED,
-            TetrominoKind::J => Color::BLUE,
-            TetrominoKind::L => Color::from_rgb(0xff, 0xa5, 0x00), //Orange,
+            TetrominoKind::I => 4,
+            TetrominoKind::O => 2,
+            _ => 3,
         }
     }
 
-    fn shape(&self) -> Vec<Vec<bool>> {
-        match self {
-            TetrominoKind::I => vec![vec![false, false, false, false], vec![true, true, true, true], vec![false, false, false, false], vec![false, false, false, false]],
-            TetrominoKind::O => vec![vec![true, true], vec![true, true]],
-            TetrominoKind::T => vec![vec![false, true, false], vec![true, true, true], vec![false, false, false]],
-            TetrominoKind::S => vec![vec![false, true, true], vec![true, true, false], vec![false, false, false]],
-            TetrominoKind::Z => vec![vec![true, true, false], vec![false, true, true], vec![false, false, false]],
-            TetrominoKind::J => vec![vec![true, false, false], vec![true, true, true], vec![false, false, false]],
-            TetrominoKind::L => vec![vec![false, false, true], vec![true, true, true], vec![false, false, false]],
+    fn shape(&self, system: &dyn RotationSystem, rotation: Rotation) -> Vec<Vec<bool>> {
+        let size = self.box_size();
+        let mut shape = vec![vec![false; size]; size];
+        for (x, y) in system.tiles(*self, rotation) {
+            shape[y][x] = true;
         }
+        shape
     }
 }
 
@@ -145,4 +108,244 @@ impl Distribution<TetrominoKind> for Standard {
             _ => panic!("Tetromino distribution - out of range")
         }
     }
-}
\ No newline at end of file
+}
+
+/// Source of the upcoming tetromino kinds.
+pub trait Randomizer {
+    fn next(&mut self) -> TetrominoKind;
+}
+
+/// Every kind is drawn independently with equal probability (the original behavior).
+pub struct UniformRandomizer {
+    rng: ChaCha8Rng,
+}
+
+impl UniformRandomizer {
+    pub fn new(rng: ChaCha8Rng) -> Self {
+        Self { rng }
+    }
+}
+
+impl Randomizer for UniformRandomizer {
+    fn next(&mut self) -> TetrominoKind {
+        self.rng.gen()
+    }
+}
+
+/// Deals shuffled bags holding `copies` of every kind, e.g. the guideline 7-bag (1 copy) or the 14-bag (2 copies).
+pub struct BagRandomizer {
+    rng: ChaCha8Rng,
+    copies: usize,
+    bag: Vec<TetrominoKind>,
+}
+
+impl BagRandomizer {
+    pub fn new(rng: ChaCha8Rng, copies: usize) -> Self {
+        Self {
+            rng,
+            copies,
+            bag: Vec::with_capacity(copies * TetrominoKind::ALL.len()),
+        }
+    }
+
+    fn refill(&mut self) {
+        for _ in 0..self.copies {
+            self.bag.extend_from_slice(&TetrominoKind::ALL);
+        }
+        self.bag.shuffle(&mut self.rng);
+    }
+}
+
+impl Randomizer for BagRandomizer {
+    fn next(&mut self) -> TetrominoKind {
+        if self.bag.is_empty() {
+            self.refill();
+        }
+        self.bag.pop().unwrap()
+    }
+}
+
+/// TGM style randomizer: keeps a history of the last 4 kinds and rerolls a few times when the drawn kind is in it.
+/// The first piece is never S, Z or O.
+pub struct HistoryRandomizer {
+    rng: ChaCha8Rng,
+    history: [TetrominoKind; 4],
+    rolls: usize,
+    first: bool,
+}
+
+impl HistoryRandomizer {
+    const FIRST_PIECES: [TetrominoKind; 4] = [TetrominoKind::I, TetrominoKind::J, TetrominoKind::L, TetrominoKind::T];
+
+    pub fn new(rng: ChaCha8Rng, rolls: usize) -> Self {
+        Self {
+            rng,
+            history: [TetrominoKind::Z, TetrominoKind::S, TetrominoKind::S, TetrominoKind::Z],
+            rolls,
+            first: true,
+        }
+    }
+
+    fn roll(&mut self) -> TetrominoKind {
+        let mut kind = self.rng.gen();
+        for _ in 1..self.rolls {
+            if !self.history.contains(&kind) {
+                break;
+            }
+            kind = self.rng.gen();
+        }
+        kind
+    }
+}
+
+impl Randomizer for HistoryRandomizer {
+    fn next(&mut self) -> TetrominoKind {
+        let kind = if self.first {
+            self.first = false;
+            *Self::FIRST_PIECES.choose(&mut self.rng).unwrap()
+        } else {
+            self.roll()
+        };
+        self.history.rotate_left(1);
+        self.history[3] = kind;
+        kind
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RandomizerKind {
+    Uniform,
+    SevenBag,
+    FourteenBag,
+    TgmHistory,
+}
+
+impl RandomizerKind {
+    /// Creates the randomizer; the same seed always produces the same sequence of kinds, on every build, because
+    /// ChaCha8's output is fixed by its specification rather than by the `rand` version.
+    pub fn create(&self, seed: u64) -> Box<dyn Randomizer> {
+        let rng = ChaCha8Rng::seed_from_u64(seed);
+        match self {
+            RandomizerKind::Uniform => Box::new(UniformRandomizer::new(rng)),
+            RandomizerKind::SevenBag => Box::new(BagRandomizer::new(rng, 1)),
+            RandomizerKind::FourteenBag => Box::new(BagRandomizer::new(rng, 2)),
+            RandomizerKind::TgmHistory => Box::new(HistoryRandomizer::new(rng, 6)),
+        }
+    }
+}
+
+impl FromStr for RandomizerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" | "classic" => Ok(RandomizerKind::Uniform),
+            "7bag" => Ok(RandomizerKind::SevenBag),
+            "14bag" => Ok(RandomizerKind::FourteenBag),
+            "tgm" => Ok(RandomizerKind::TgmHistory),
+            _ => Err(format!("unknown randomizer '{}', expected one of: uniform, 7bag, 14bag, tgm", s))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(pieces: &[TetrominoKind], kind: TetrominoKind) -> usize {
+        pieces.iter().filter(|&&x| x == kind).count()
+    }
+
+    #[test]
+    fn seven_bag_contains_every_kind_once() {
+        let mut randomizer = RandomizerKind::SevenBag.create(1);
+        for _ in 0..100 {
+            let bag: Vec<TetrominoKind> = (0..7).map(|_| randomizer.next()).collect();
+            for kind in TetrominoKind::ALL {
+                assert_eq!(count(&bag, kind), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn fourteen_bag_contains_every_kind_twice() {
+        let mut randomizer = RandomizerKind::FourteenBag.create(2);
+        for _ in 0..100 {
+            let bag: Vec<TetrominoKind> = (0..14).map(|_| randomizer.next()).collect();
+            for kind in TetrominoKind::ALL {
+                assert_eq!(count(&bag, kind), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn seven_bag_drought_is_bounded() {
+        let mut randomizer = RandomizerKind::SevenBag.create(3);
+        let mut since_last_i = 0;
+        for _ in 0..10_000 {
+            if randomizer.next() == TetrominoKind::I {
+                since_last_i = 0;
+            } else {
+                since_last_i += 1;
+            }
+            assert!(since_last_i <= 12);
+        }
+    }
+
+    #[test]
+    fn tgm_history_first_piece_is_never_s_z_or_o() {
+        for seed in 0..1000 {
+            let first = RandomizerKind::TgmHistory.create(seed).next();
+            assert!(HistoryRandomizer::FIRST_PIECES.contains(&first));
+        }
+    }
+
+    #[test]
+    fn tgm_history_repeats_less_than_uniform() {
+        let mut randomizer = RandomizerKind::TgmHistory.create(4);
+        let pieces: Vec<TetrominoKind> = (0..7000).map(|_| randomizer.next()).collect();
+        let repeats = pieces.windows(2).filter(|w| w[0] == w[1]).count();
+        // a uniform source repeats ~1/7 of the time (~1000 here), six rolls against a 4-history almost never do
+        assert!(repeats < 200, "{} repeats", repeats);
+        for kind in TetrominoKind::ALL {
+            assert!(count(&pieces, kind) > 0);
+        }
+    }
+
+    #[test]
+    fn same_seed_produces_same_sequence() {
+        for kind in [RandomizerKind::Uniform, RandomizerKind::SevenBag, RandomizerKind::FourteenBag, RandomizerKind::TgmHistory] {
+            let mut first = kind.create(42);
+            let mut second = kind.create(42);
+            for _ in 0..1000 {
+                assert_eq!(first.next(), second.next());
+            }
+        }
+    }
+
+    #[test]
+    fn seed_sequences_are_pinned() {
+        use TetrominoKind::*;
+        // replays and shared seeds rely on these, a change here breaks every saved replay
+        let expected = [
+            (RandomizerKind::Uniform, [O, Z, O, L, J, T, Z, J, T, Z, O, T, T, O]),
+            (RandomizerKind::SevenBag, [O, Z, T, L, J, S, I, O, Z, J, T, L, S, I]),
+            (RandomizerKind::FourteenBag, [S, J, T, O, L, O, T, S, Z, J, I, Z, L, I]),
+            (RandomizerKind::TgmHistory, [T, J, O, J, S, T, L, Z, J, O, I, S, L, Z]),
+        ];
+        for (kind, pieces) in expected {
+            let mut randomizer = kind.create(42);
+            let actual: Vec<TetrominoKind> = (0..pieces.len()).map(|_| randomizer.next()).collect();
+            assert_eq!(actual, pieces, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn uniform_produces_every_kind() {
+        let mut randomizer = RandomizerKind::Uniform.create(5);
+        let pieces: Vec<TetrominoKind> = (0..7000).map(|_| randomizer.next()).collect();
+        for kind in TetrominoKind::ALL {
+            assert!(count(&pieces, kind) > 700);
+        }
+    }
+}
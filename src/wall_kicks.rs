@@ -1,29 +1,99 @@
+use std::str::FromStr;
+
 use rusttype::Point;
+use serde::{Deserialize, Serialize};
 
-pub static WALL_KICKS : [[Point<i32>; 5]; 8] = [
-    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: -1, y: -1}, Point {x: 0, y: 2}, Point {x: -1, y: 2} ],
-    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: 1, y: 1}, Point {x: 0, y: -2}, Point {x: 1, y: -2} ],
+use crate::rotation::Rotation;
 
-    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: 1, y: 1}, Point {x: 0, y: -2}, Point {x: 1, y: -2} ],
-    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: -1, y: -1}, Point {x: 0, y: 2}, Point {x: -1, y: 2} ],
+// Offsets are written as in the SRS references, with y growing upward; `GameState` flips them for the board, whose
+// y grows downward. Rows of the 90° tables come in pairs: 0->R, R->0, R->2, 2->R, 2->L, L->2, L->0, 0->L.
 
-    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: 1, y: -1}, Point {x: 0, y: 2}, Point {x: 1, y: 2} ],
+/// SRS kicks of the J, L, S, T and Z tetrominoes.
+pub static WALL_KICKS : [[Point<i32>; 5]; 8] = [
     [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: -1, y: 1}, Point {x: 0, y: -2}, Point {x: -1, y: -2} ],
+    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: 1, y: -1}, Point {x: 0, y: 2}, Point {x: 1, y: 2} ],
 
-    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: -1, y: 1}, Point {x: 0, y: -2}, Point {x: -1, y: -2} ],
     [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: 1, y: -1}, Point {x: 0, y: 2}, Point {x: 1, y: 2} ],
+    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: -1, y: 1}, Point {x: 0, y: -2}, Point {x: -1, y: -2} ],
+
+    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: 1, y: 1}, Point {x: 0, y: -2}, Point {x: 1, y: -2} ],
+    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: -1, y: -1}, Point {x: 0, y: 2}, Point {x: -1, y: 2} ],
+
+    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: -1, y: -1}, Point {x: 0, y: 2}, Point {x: -1, y: 2} ],
+    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: 1, y: 1}, Point {x: 0, y: -2}, Point {x: 1, y: -2} ],
 ];
 
+/// SRS kicks of the I tetromino.
 pub static I_WALL_KICKS : [[Point<i32>; 5]; 8] = [
-    [Point {x: 0, y: 0}, Point {x: -2, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: 1}, Point {x: 1, y: -2} ],
-    [Point {x: 0, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: -1}, Point {x: -1, y: 2} ],
+    [Point {x: 0, y: 0}, Point {x: -2, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: -1}, Point {x: 1, y: 2} ],
+    [Point {x: 0, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: 1}, Point {x: -1, y: -2} ],
+
+    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: 2}, Point {x: 2, y: -1} ],
+    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: 0}, Point {x: 1, y: -2}, Point {x: -2, y: 1} ],
+
+    [Point {x: 0, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: 1}, Point {x: -1, y: -2} ],
+    [Point {x: 0, y: 0}, Point {x: -2, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: -1}, Point {x: 1, y: 2} ],
+
+    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: 0}, Point {x: 1, y: -2}, Point {x: -2, y: 1} ],
+    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: 2}, Point {x: 2, y: -1} ],
+];
 
+/// TETR.IO's SRS+ kicks of the I tetromino, mirrored between clockwise and counterclockwise rotations.
+pub static SRS_PLUS_I_WALL_KICKS : [[Point<i32>; 5]; 8] = [
+    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: 0}, Point {x: -2, y: -1}, Point {x: 1, y: 2} ],
     [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: -2}, Point {x: 2, y: 1} ],
-    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: 0}, Point {x: 1, y: 2}, Point {x: -2, y: -1} ],
 
-    [Point {x: 0, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: -1}, Point {x: -1, y: 2} ],
+    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: 2}, Point {x: 2, y: -1} ],
     [Point {x: 0, y: 0}, Point {x: -2, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: 1}, Point {x: 1, y: -2} ],
 
+    [Point {x: 0, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: 1}, Point {x: -1, y: -2} ],
     [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: 0}, Point {x: 1, y: 2}, Point {x: -2, y: -1} ],
-    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: 0}, Point {x: -1, y: -2}, Point {x: 2, y: 1} ],
-];
\ No newline at end of file
+
+    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: -2, y: 0}, Point {x: 1, y: -2}, Point {x: -2, y: 1} ],
+    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: 2, y: 0}, Point {x: 2, y: -1}, Point {x: -1, y: 2} ],
+];
+
+/// TETR.IO's SRS+ kicks of 180° rotations, the same for every tetromino, indexed by the starting rotation (0, R, 2, L).
+pub static HALF_WALL_KICKS : [[Point<i32>; 6]; 4] = [
+    [Point {x: 0, y: 0}, Point {x: 0, y: 1}, Point {x: 1, y: 1}, Point {x: -1, y: 1}, Point {x: 1, y: 0}, Point {x: -1, y: 0} ],
+    [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: 1, y: 2}, Point {x: 1, y: 1}, Point {x: 0, y: 2}, Point {x: 0, y: 1} ],
+    [Point {x: 0, y: 0}, Point {x: 0, y: -1}, Point {x: -1, y: -1}, Point {x: 1, y: -1}, Point {x: -1, y: 0}, Point {x: 1, y: 0} ],
+    [Point {x: 0, y: 0}, Point {x: -1, y: 0}, Point {x: -1, y: 2}, Point {x: -1, y: 1}, Point {x: 0, y: 2}, Point {x: 0, y: 1} ],
+];
+
+/// ARS kicks: in place, one tile right, one tile left.
+pub static ARS_WALL_KICKS : [Point<i32>; 3] = [Point {x: 0, y: 0}, Point {x: 1, y: 0}, Point {x: -1, y: 0}];
+
+/// The tetromino only turns in place.
+pub static NO_WALL_KICKS : [Point<i32>; 1] = [Point {x: 0, y: 0}];
+
+/// Wall kicks tried when rotating by 180°.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum HalfKickTable {
+    /// The tetromino only turns in place.
+    None,
+    /// SRS+ as in TETR.IO, see `HALF_WALL_KICKS`.
+    SrsPlus,
+}
+
+impl HalfKickTable {
+    /// Offsets tried in order for a 180° rotation starting from `start`.
+    pub fn kicks(&self, start: Rotation) -> &'static [Point<i32>] {
+        match self {
+            HalfKickTable::None => &NO_WALL_KICKS,
+            HalfKickTable::SrsPlus => &HALF_WALL_KICKS[start as usize],
+        }
+    }
+}
+
+impl FromStr for HalfKickTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(HalfKickTable::None),
+            "srs+" => Ok(HalfKickTable::SrsPlus),
+            _ => Err(format!("unknown 180 kick table '{}', expected one of: none, srs+", s))
+        }
+    }
+}